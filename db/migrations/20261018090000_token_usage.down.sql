@@ -0,0 +1,3 @@
+-- 18 10 2026: token_usage Down Migration
+
+DROP TABLE IF EXISTS token_usage CASCADE;
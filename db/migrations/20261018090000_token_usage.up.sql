@@ -0,0 +1,15 @@
+-- 18 10 2026: token_usage Up Migration
+
+CREATE TABLE token_usage (
+    id SERIAL PRIMARY KEY,
+    endpoint TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    -- NULL when the provider doesn't report usage, e.g. streamed completions
+    prompt_tokens INTEGER,
+    completion_tokens INTEGER,
+    total_tokens INTEGER,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX token_usage_created_at_idx ON token_usage (created_at);
@@ -0,0 +1,7 @@
+-- 18 10 2026: token_usage_cost Down Migration
+
+DROP INDEX IF EXISTS token_usage_project_created_at_idx;
+
+ALTER TABLE token_usage
+    DROP COLUMN IF EXISTS project,
+    DROP COLUMN IF EXISTS cost_usd;
@@ -0,0 +1,8 @@
+-- 18 10 2026: token_usage_cost Up Migration
+
+ALTER TABLE token_usage
+    ADD COLUMN project TEXT NOT NULL DEFAULT 'default',
+    -- NULL when the usage is unknown
+    ADD COLUMN cost_usd DOUBLE PRECISION;
+
+CREATE INDEX token_usage_project_created_at_idx ON token_usage (project, created_at);
@@ -0,0 +1,8 @@
+-- 18 10 2026: key_value_cache Down Migration
+
+DROP INDEX IF EXISTS key_value_store_expires_at_idx;
+DROP INDEX IF EXISTS key_value_store_key_idx;
+
+ALTER TABLE key_value_store
+    DROP COLUMN IF EXISTS key,
+    DROP COLUMN IF EXISTS expires_at;
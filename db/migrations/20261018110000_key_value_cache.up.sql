@@ -0,0 +1,9 @@
+-- 18 10 2026: key_value_cache Up Migration
+
+ALTER TABLE key_value_store
+    ADD COLUMN key TEXT,
+    -- NULL never expires
+    ADD COLUMN expires_at TIMESTAMP WITH TIME ZONE;
+
+CREATE UNIQUE INDEX key_value_store_key_idx ON key_value_store (key);
+CREATE INDEX key_value_store_expires_at_idx ON key_value_store (expires_at);
@@ -0,0 +1,6 @@
+-- 18 10 2026: api_keys Down Migration
+
+ALTER TABLE token_usage
+    DROP COLUMN IF EXISTS api_key_id;
+
+DROP TABLE IF EXISTS api_keys CASCADE;
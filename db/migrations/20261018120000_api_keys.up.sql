@@ -0,0 +1,17 @@
+-- 18 10 2026: api_keys Up Migration
+
+CREATE TABLE api_keys (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- Leading characters of the key, to tell keys apart without storing them
+    key_prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    project TEXT NOT NULL DEFAULT 'default',
+    metadata JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+ALTER TABLE token_usage
+    ADD COLUMN api_key_id INTEGER REFERENCES api_keys (id);
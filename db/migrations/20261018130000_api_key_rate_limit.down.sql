@@ -0,0 +1,4 @@
+-- 18 10 2026: api_key_rate_limit Down Migration
+
+ALTER TABLE api_keys
+    DROP COLUMN IF EXISTS rate_limit;
@@ -0,0 +1,5 @@
+-- 18 10 2026: api_key_rate_limit Up Migration
+
+-- Per key override of the default key rate limit, e.g. {"requests_per_minute": 60, "burst": 10}
+ALTER TABLE api_keys
+    ADD COLUMN rate_limit JSONB;
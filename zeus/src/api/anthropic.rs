@@ -0,0 +1,607 @@
+use std::{borrow::Cow, sync::Arc};
+
+use async_trait::async_trait;
+use futures_util::{future, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        circuit_breaker::CircuitBreaker,
+        llm_provider::{
+            ChatDelta, ChatRequest, ChatResponse, ChatStream, LlmProvider, ProviderCapabilities,
+            TokenUsage,
+        },
+        model_catalog::{ModelPrice, ModelSpec},
+        provider_client::ProviderClient,
+    },
+    models::{chat_message::ChatRole, custom_error::CustomError},
+    repository::{prompt_provider::Prompt, secrets::Secrets},
+    utils::{image_utils::ImageUtils, sse_utils::SseUtils},
+};
+
+pub struct AnthropicApi {
+    client: ProviderClient,
+    base_url: String,
+}
+
+impl AnthropicApi {
+    pub const NAME: &'static str = "anthropic";
+    const MESSAGES_PATH: &'static str = "/v1/messages";
+    const API_VERSION: &'static str = "2023-06-01";
+    const MAX_TOKENS: u32 = 4096;
+    const DEFAULT_MODEL: &'static str = "claude-3-sonnet-20240229";
+    const MODELS: [ModelSpec; 4] = [
+        Self::model_spec("claude-3-opus-20240229", ModelPrice::new(15.0, 75.0)),
+        Self::model_spec(Self::DEFAULT_MODEL, ModelPrice::new(3.0, 15.0)),
+        Self::model_spec("claude-3-haiku-20240307", ModelPrice::new(0.25, 1.25)),
+        Self::model_spec("claude-2.1", ModelPrice::new(8.0, 24.0)),
+    ];
+
+    const fn model_spec(name: &'static str, price: ModelPrice) -> ModelSpec {
+        ModelSpec {
+            name: Cow::Borrowed(name),
+            max_output_tokens: Self::MAX_TOKENS,
+            max_temperature: 1.0,
+            max_stop_sequences: 8,
+            supports_seed: false,
+            supports_top_k: true,
+            supports_json_mode: false,
+            price,
+        }
+    }
+
+    // None when no Anthropic key is configured
+    pub fn new(secrets: &Secrets) -> Result<Option<Self>, CustomError> {
+        let Some(api_key) = secrets.anthropic_api_key.as_ref() else {
+            return Ok(None);
+        };
+        let api_key = reqwest::header::HeaderValue::from_str(api_key).map_err(|_| {
+            CustomError::Configuration("ANTHROPIC_API_KEY is not a valid header value".to_string())
+        })?;
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-api-key", api_key);
+        headers.insert(
+            "anthropic-version",
+            reqwest::header::HeaderValue::from_static(Self::API_VERSION),
+        );
+        Ok(Some(Self {
+            client: ProviderClient::new(Self::NAME, secrets, headers),
+            base_url: secrets.anthropic_base_url.clone(),
+        }))
+    }
+
+    pub fn circuit(&self) -> Arc<CircuitBreaker> {
+        self.client.circuit()
+    }
+
+    pub async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, CustomError> {
+        let payload = Self::chat_payload(request, None)?;
+        let message: MessageResponse = self.send(&payload).await?.json().await?;
+        Ok(Self::chat_response(message))
+    }
+
+    fn chat_response(message: MessageResponse) -> ChatResponse {
+        ChatResponse {
+            provider: Self::NAME.to_string(),
+            content: message.text(),
+            finish_reason: message.finish_reason(),
+            usage: Some(message.usage.token_usage()),
+            model: message.model,
+            safety_ratings: Vec::new(),
+            tool_calls: Vec::new(),
+            cost_usd: None,
+            cached: false,
+        }
+    }
+
+    pub async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream, CustomError> {
+        let payload = Self::chat_payload(request, Some(true))?;
+        let response = self.send(&payload).await?;
+        Ok(SseUtils::data_events(response)
+            .and_then(|data| async move { Ok(serde_json::from_str::<StreamEvent>(&data)?) })
+            // The input tokens come with `message_start`, the output tokens with `message_delta`
+            .scan(0, |input_tokens, event| {
+                let delta = match event {
+                    Ok(StreamEvent::MessageStart { message }) => {
+                        *input_tokens = message.usage.input_tokens;
+                        Ok(None)
+                    }
+                    Ok(StreamEvent::ContentBlockDelta {
+                        delta: Delta::TextDelta { text },
+                    }) if !text.is_empty() => Ok(Some(ChatDelta::Text(text))),
+                    Ok(StreamEvent::MessageDelta { usage }) => Ok(Some(ChatDelta::Usage(
+                        Usage {
+                            input_tokens: *input_tokens,
+                            output_tokens: usage.output_tokens,
+                        }
+                        .token_usage(),
+                    ))),
+                    Ok(StreamEvent::Error { error }) => {
+                        log::error!("Error event: {:?}", error);
+                        Err(CustomError::NoContentFromAssistant)
+                    }
+                    Ok(_) => Ok(None),
+                    Err(e) => Err(e),
+                };
+                future::ready(Some(delta))
+            })
+            .try_filter_map(future::ok)
+            .boxed())
+    }
+
+    pub async fn visual(&self, prompt: Prompt, base64_image: &str) -> Result<ChatResponse, CustomError> {
+        let payload = Payload {
+            model: Self::DEFAULT_MODEL.to_string(),
+            system: Some(prompt.prompt()),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: vec![ContentBlock::Image {
+                    source: ImageSource::base64(base64_image),
+                }],
+            }],
+            max_tokens: Self::MAX_TOKENS,
+            temperature: None,
+            top_p: None,
+            top_k: None,
+            stop_sequences: None,
+            stream: None,
+        };
+        let message: MessageResponse = self.send(&payload).await?.json().await?;
+        Ok(Self::chat_response(message))
+    }
+
+    // System messages move to the top level `system` field and repeated roles are merged,
+    // as Anthropic expects alternating user/assistant turns. JSON response formats are
+    // enforced by the gateway through instructions, tools are not supported
+    fn chat_payload(request: &ChatRequest, stream: Option<bool>) -> Result<Payload, CustomError> {
+        if request.uses_tools() {
+            return Err(CustomError::InvalidRequest(format!(
+                "{} does not support tool calling",
+                Self::NAME
+            )));
+        }
+        let system = request
+            .messages
+            .iter()
+            .filter(|message| message.role == ChatRole::System)
+            .map(|message| message.content.as_str())
+            .collect::<Vec<&str>>()
+            .join("\n");
+        let mut messages: Vec<Message> = Vec::new();
+        for message in request
+            .messages
+            .iter()
+            .filter(|message| message.role != ChatRole::System)
+        {
+            let role = match message.role {
+                ChatRole::Assistant => "assistant",
+                ChatRole::System | ChatRole::User | ChatRole::Tool => "user",
+            };
+            let block = ContentBlock::Text {
+                text: message.content.clone(),
+            };
+            match messages.last_mut() {
+                Some(last) if last.role == role => last.content.push(block),
+                _ => messages.push(Message {
+                    role: role.to_string(),
+                    content: vec![block],
+                }),
+            }
+        }
+
+        Ok(Payload {
+            model: request.model.clone(),
+            system: if system.is_empty() { None } else { Some(system) },
+            messages,
+            max_tokens: request.params.max_tokens.unwrap_or(Self::MAX_TOKENS),
+            temperature: request.params.temperature,
+            top_p: request.params.top_p,
+            top_k: request.params.top_k,
+            stop_sequences: request.params.stop.clone(),
+            stream,
+        })
+    }
+
+    async fn send(&self, payload: &Payload) -> Result<reqwest::Response, CustomError> {
+        let url = format!("{}{}", self.base_url, Self::MESSAGES_PATH);
+        let response = self.client.send(self.client.post(url).json(payload)).await?;
+
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let code = response.status().as_u16();
+        match response.json::<ErrorResponse>().await {
+            Ok(ErrorResponse { error }) => {
+                log::error!("Error response: {} {}", error.type_, error.message);
+                match error.type_.as_str() {
+                    "invalid_request_error" => Err(CustomError::InvalidRequest(error.message)),
+                    // Anthropic reports overload with the non standard 529
+                    "overloaded_error" => Err(CustomError::NonSuccessfulResponse(503)),
+                    _ => Err(CustomError::NonSuccessfulResponse(code)),
+                }
+            }
+            Err(e) => {
+                log::error!("Error response: {}", e);
+                Err(CustomError::NonSuccessfulResponse(code))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicApi {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            completion: true,
+            vision: true,
+            embedding: false,
+            streaming: true,
+            tools: false,
+            translation: false,
+        }
+    }
+
+    fn models(&self) -> Vec<ModelSpec> {
+        Self::MODELS.to_vec()
+    }
+
+    fn default_model(&self) -> &str {
+        Self::DEFAULT_MODEL
+    }
+
+    fn vision_model(&self) -> Option<&str> {
+        Some(Self::DEFAULT_MODEL)
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, CustomError> {
+        self.chat(request).await
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream, CustomError> {
+        self.chat_stream(request).await
+    }
+
+    async fn visual(&self, prompt: Prompt, base64_image: &str) -> Result<ChatResponse, CustomError> {
+        self.visual(prompt, base64_image).await
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct Payload {
+    model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<Message>,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Message {
+    role: String,
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text { text: String },
+    Image { source: ImageSource },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ImageSource {
+    #[serde(rename = "type")]
+    type_: String,
+    media_type: String,
+    data: String,
+}
+
+impl ImageSource {
+    fn base64(base64: &str) -> Self {
+        Self {
+            type_: "base64".to_string(),
+            media_type: ImageUtils::media_type(base64).to_string(),
+            data: base64.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct MessageResponse {
+    model: String,
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+    usage: Usage,
+}
+
+impl MessageResponse {
+    fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                ContentBlock::Image { .. } => None,
+            })
+            .collect::<Vec<&str>>()
+            .join("")
+    }
+
+    // Maps Anthropic stop reasons onto the OpenAI vocabulary
+    fn finish_reason(&self) -> Option<String> {
+        self.stop_reason.as_deref().map(|reason| {
+            match reason {
+                "max_tokens" => "length",
+                _ => "stop",
+            }
+            .to_string()
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Usage {
+    input_tokens: u32,
+    output_tokens: u32,
+}
+
+impl Usage {
+    fn token_usage(&self) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens: self.input_tokens,
+            completion_tokens: self.output_tokens,
+            total_tokens: self.input_tokens + self.output_tokens,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart { message: StreamMessage },
+    ContentBlockDelta { delta: Delta },
+    MessageDelta { usage: DeltaUsage },
+    Error { error: ErrorDetail },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamMessage {
+    usage: Usage,
+}
+
+// Output tokens so far, the input tokens are only sent with `message_start`
+#[derive(Deserialize, Debug)]
+struct DeltaUsage {
+    output_tokens: u32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Delta {
+    TextDelta { text: String },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorDetail {
+    #[serde(rename = "type")]
+    type_: String,
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_partial_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::models::chat_message::ChatMessage;
+
+    async fn api(server: &MockServer) -> AnthropicApi {
+        let secrets = Secrets {
+            anthropic_api_key: Some("test-key".to_string()),
+            anthropic_base_url: server.uri(),
+            ..Secrets::default()
+        };
+        AnthropicApi::new(&secrets).unwrap().unwrap()
+    }
+
+    fn request() -> ChatRequest {
+        ChatRequest {
+            model: AnthropicApi::DEFAULT_MODEL.to_string(),
+            messages: vec![
+                ChatMessage::new(ChatRole::System, "Be brief".to_string()),
+                ChatMessage::new(ChatRole::User, "Hello".to_string()),
+            ],
+            params: Default::default(),
+            tools: Vec::new(),
+            tool_choice: None,
+            response_format: None,
+            safety_settings: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_maps_the_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(AnthropicApi::MESSAGES_PATH))
+            .and(header("x-api-key", "test-key"))
+            .and(header("anthropic-version", AnthropicApi::API_VERSION))
+            .and(body_partial_json(json!({
+                "system": "Be brief",
+                "messages": [{"role": "user", "content": [{"type": "text", "text": "Hello"}]}],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": AnthropicApi::DEFAULT_MODEL,
+                "content": [{"type": "text", "text": "Hi there"}],
+                "stop_reason": "max_tokens",
+                "usage": {"input_tokens": 12, "output_tokens": 3},
+            })))
+            .mount(&server)
+            .await;
+
+        let response = api(&server).await.chat(&request()).await.unwrap();
+
+        assert_eq!(response.content, "Hi there");
+        assert_eq!(response.finish_reason.as_deref(), Some("length"));
+        let usage = response.usage.unwrap();
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens, usage.total_tokens), (12, 3, 15));
+    }
+
+    #[tokio::test]
+    async fn chat_stream_yields_text_and_usage() {
+        let server = MockServer::start().await;
+        let body = [
+            r#"{"type":"message_start","message":{"usage":{"input_tokens":12,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" there"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":3}}"#,
+            r#"{"type":"message_stop"}"#,
+        ]
+        .iter()
+        .map(|data| format!("event: message\ndata: {}\n\n", data))
+        .collect::<String>();
+        Mock::given(method("POST"))
+            .and(path(AnthropicApi::MESSAGES_PATH))
+            .and(body_partial_json(json!({"stream": true})))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(body),
+            )
+            .mount(&server)
+            .await;
+
+        let deltas: Vec<ChatDelta> = api(&server)
+            .await
+            .chat_stream(&request())
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        let text: String = deltas
+            .iter()
+            .filter_map(|delta| match delta {
+                ChatDelta::Text(text) => Some(text.as_str()),
+                ChatDelta::Usage(_) => None,
+            })
+            .collect();
+        assert_eq!(text, "Hi there");
+        match deltas.last() {
+            Some(ChatDelta::Usage(usage)) => {
+                assert_eq!((usage.prompt_tokens, usage.completion_tokens), (12, 3))
+            }
+            other => panic!("expected usage last, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_stream_fails_on_error_event() {
+        let server = MockServer::start().await;
+        let body = "data: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n";
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+
+        let result: Result<Vec<ChatDelta>, CustomError> =
+            api(&server).await.chat_stream(&request()).await.unwrap().try_collect().await;
+
+        assert!(matches!(result, Err(CustomError::NoContentFromAssistant)));
+    }
+
+    #[tokio::test]
+    async fn invalid_request_errors_keep_the_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "type": "error",
+                "error": {"type": "invalid_request_error", "message": "max_tokens: too large"},
+            })))
+            .mount(&server)
+            .await;
+
+        let result = api(&server).await.chat(&request()).await;
+
+        assert!(matches!(result, Err(CustomError::InvalidRequest(message)) if message == "max_tokens: too large"));
+    }
+
+    #[tokio::test]
+    async fn overloaded_maps_to_service_unavailable() {
+        let server = MockServer::start().await;
+        // The mock server can't send Anthropic's 529, the error type decides the mapping
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500).set_body_json(json!({
+                "type": "error",
+                "error": {"type": "overloaded_error", "message": "Overloaded"},
+            })))
+            .mount(&server)
+            .await;
+
+        let result = api(&server).await.chat(&request()).await;
+
+        assert!(matches!(result, Err(CustomError::NonSuccessfulResponse(503))));
+    }
+
+    #[tokio::test]
+    async fn unparsable_errors_keep_the_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("Unauthorized"))
+            .mount(&server)
+            .await;
+
+        let result = api(&server).await.chat(&request()).await;
+
+        assert!(matches!(result, Err(CustomError::NonSuccessfulResponse(401))));
+    }
+
+    #[test]
+    fn invalid_api_key_is_a_configuration_error() {
+        let secrets = Secrets {
+            anthropic_api_key: Some("bad\nkey".to_string()),
+            ..Secrets::default()
+        };
+        assert!(matches!(AnthropicApi::new(&secrets), Err(CustomError::Configuration(_))));
+    }
+
+    #[test]
+    fn image_source_detects_the_media_type() {
+        use base64::{engine::general_purpose, Engine};
+        let png = general_purpose::STANDARD.encode(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR");
+        let webp = general_purpose::STANDARD.encode(b"RIFF\0\0\0\0WEBPVP8 ");
+        let jpeg = general_purpose::STANDARD.encode(b"\xff\xd8\xff\xe0\0\x10JFIF");
+        assert_eq!(ImageSource::base64(&png).media_type, "image/png");
+        assert_eq!(ImageSource::base64(&webp).media_type, "image/webp");
+        assert_eq!(ImageSource::base64(&jpeg).media_type, "image/jpeg");
+    }
+}
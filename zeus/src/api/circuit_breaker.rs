@@ -0,0 +1,220 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{models::custom_error::CustomError, repository::secrets::Secrets};
+
+// Opens after repeated upstream failures and short-circuits calls until the cool-down passes
+pub struct CircuitBreaker {
+    name: String,
+    failure_threshold: u32,
+    cool_down: Duration,
+    circuit: Mutex<Circuit>,
+}
+
+#[derive(Default)]
+struct Circuit {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    // The half open trial call in flight, if any
+    probe_started_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &str, secrets: &Secrets) -> Self {
+        Self {
+            name: name.to_string(),
+            failure_threshold: secrets.circuit_failure_threshold.max(1),
+            cool_down: Duration::from_millis(secrets.circuit_cool_down_ms),
+            circuit: Mutex::new(Circuit::default()),
+        }
+    }
+
+    // Half open lets a single trial call through, its failure opens the circuit again.
+    // A trial that never reports back is given up after another cool-down
+    pub fn acquire(&self) -> Result<(), CustomError> {
+        let mut circuit = self.circuit.lock().unwrap();
+        match Self::status(self.remaining(&circuit)) {
+            CircuitStatus::Closed => Ok(()),
+            CircuitStatus::HalfOpen
+                if circuit
+                    .probe_started_at
+                    .is_none_or(|started_at| started_at.elapsed() >= self.cool_down) =>
+            {
+                circuit.probe_started_at = Some(Instant::now());
+                Ok(())
+            }
+            CircuitStatus::HalfOpen | CircuitStatus::Open => {
+                Err(CustomError::CircuitOpen(self.name.clone()))
+            }
+        }
+    }
+
+    pub fn record(&self, healthy: bool) {
+        let mut circuit = self.circuit.lock().unwrap();
+        if healthy {
+            *circuit = Circuit::default();
+            return;
+        }
+        circuit.consecutive_failures += 1;
+        if circuit.consecutive_failures >= self.failure_threshold {
+            if circuit.opened_at.is_none() {
+                log::warn!("Circuit for {} opened", self.name);
+            }
+            circuit.opened_at = Some(Instant::now());
+            circuit.probe_started_at = None;
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let circuit = self.circuit.lock().unwrap();
+        let remaining = self.remaining(&circuit);
+        CircuitState {
+            provider: self.name.clone(),
+            status: Self::status(remaining),
+            consecutive_failures: circuit.consecutive_failures,
+            retry_in_ms: remaining
+                .filter(|remaining| !remaining.is_zero())
+                .map(|remaining| remaining.as_millis() as u64),
+        }
+    }
+
+    // Time left until half open, None while closed
+    fn remaining(&self, circuit: &Circuit) -> Option<Duration> {
+        circuit
+            .opened_at
+            .map(|opened_at| self.cool_down.saturating_sub(opened_at.elapsed()))
+    }
+
+    fn status(remaining: Option<Duration>) -> CircuitStatus {
+        match remaining {
+            None => CircuitStatus::Closed,
+            Some(remaining) if remaining.is_zero() => CircuitStatus::HalfOpen,
+            Some(_) => CircuitStatus::Open,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitStatus {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CircuitState {
+    pub provider: String,
+    pub status: CircuitStatus,
+    pub consecutive_failures: u32,
+    pub retry_in_ms: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOL_DOWN: Duration = Duration::from_millis(50);
+
+    fn breaker() -> CircuitBreaker {
+        let secrets = Secrets {
+            circuit_failure_threshold: 2,
+            circuit_cool_down_ms: COOL_DOWN.as_millis() as u64,
+            ..Secrets::default()
+        };
+        CircuitBreaker::new("test", &secrets)
+    }
+
+    fn open(breaker: &CircuitBreaker) {
+        breaker.record(false);
+        breaker.record(false);
+    }
+
+    fn half_open(breaker: &CircuitBreaker) {
+        open(breaker);
+        std::thread::sleep(COOL_DOWN);
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = breaker();
+        breaker.record(false);
+        assert_eq!(breaker.state().status, CircuitStatus::Closed);
+        assert!(breaker.acquire().is_ok());
+
+        breaker.record(false);
+        let state = breaker.state();
+        assert_eq!(state.status, CircuitStatus::Open);
+        assert_eq!(state.consecutive_failures, 2);
+        assert!(state.retry_in_ms.is_some());
+        assert!(matches!(breaker.acquire(), Err(CustomError::CircuitOpen(name)) if name == "test"));
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let breaker = breaker();
+        breaker.record(false);
+        breaker.record(true);
+        breaker.record(false);
+        assert_eq!(breaker.state().status, CircuitStatus::Closed);
+    }
+
+    #[test]
+    fn half_opens_after_the_cool_down() {
+        let breaker = breaker();
+        half_open(&breaker);
+        let state = breaker.state();
+        assert_eq!(state.status, CircuitStatus::HalfOpen);
+        assert_eq!(state.retry_in_ms, None);
+    }
+
+    #[test]
+    fn half_open_allows_a_single_probe() {
+        let breaker = breaker();
+        half_open(&breaker);
+        assert!(breaker.acquire().is_ok());
+        assert!(breaker.acquire().is_err());
+        assert!(breaker.acquire().is_err());
+    }
+
+    #[test]
+    fn successful_probe_closes_the_circuit() {
+        let breaker = breaker();
+        half_open(&breaker);
+        breaker.acquire().unwrap();
+        breaker.record(true);
+
+        assert_eq!(breaker.state().status, CircuitStatus::Closed);
+        assert!(breaker.acquire().is_ok());
+        assert!(breaker.acquire().is_ok());
+    }
+
+    #[test]
+    fn failed_probe_opens_the_circuit_again() {
+        let breaker = breaker();
+        half_open(&breaker);
+        breaker.acquire().unwrap();
+        breaker.record(false);
+
+        assert_eq!(breaker.state().status, CircuitStatus::Open);
+        assert!(breaker.acquire().is_err());
+
+        std::thread::sleep(COOL_DOWN);
+        assert!(breaker.acquire().is_ok());
+    }
+
+    #[test]
+    fn abandoned_probe_is_replaced_after_the_cool_down() {
+        let breaker = breaker();
+        half_open(&breaker);
+        breaker.acquire().unwrap();
+        assert!(breaker.acquire().is_err());
+
+        std::thread::sleep(COOL_DOWN);
+        assert!(breaker.acquire().is_ok());
+    }
+}
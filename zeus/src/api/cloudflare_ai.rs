@@ -1,17 +1,70 @@
+use std::{borrow::Cow, sync::Arc};
+
 use crate::{
-    models::{custom_error::CustomError, embedding_body_request::EmbeddintBodyRequest},
-    repository::{prompt_provider::Prompt, secrets::Secrets},
+    api::{
+        circuit_breaker::CircuitBreaker,
+        llm_provider::{
+            ChatDelta, ChatRequest, ChatResponse, ChatStream, LlmProvider, ProviderCapabilities,
+            TokenUsage,
+        },
+        model_catalog::{ModelPrice, ModelSpec},
+        provider_client::ProviderClient,
+    },
+    models::{
+        chat_message::ChatRole, custom_error::CustomError,
+        embedding_body_request::EmbeddintBodyRequest,
+        image_generation::{GeneratedImageData, ImageGenerationRequest},
+        transcription::{Transcription, TranscriptionSegment},
+        translation::{TranslationRequest, TranslationResponse},
+    },
+    repository::secrets::Secrets,
+    utils::sse_utils::SseUtils,
 };
+use async_trait::async_trait;
+use futures_util::{future, stream, StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
 
 pub struct CloudflareApi {
-    client: reqwest::Client,
+    client: ProviderClient,
     account: String,
 }
 
 impl CloudflareApi {
+    pub const NAME: &'static str = "cloudflare";
     const API_URL: &'static str =
-        "https://api.cloudflare.com/client/v4/accounts/{account}/ai/run/@cf/{model}";
+        "https://api.cloudflare.com/client/v4/accounts/{account}/ai/run/{model}";
+    const MODELS: [ModelSpec; 3] = [
+        Self::model_spec("@cf/meta/llama-2-7b-chat-int8"),
+        Self::model_spec("@cf/meta/llama-2-7b-chat-fp16"),
+        Self::model_spec("@cf/mistral/mistral-7b-instruct-v0.1"),
+    ];
+    // ISO 639 codes known to m2m100
+    const TRANSLATION_LANGUAGES: [&'static str; 100] = [
+        "af", "am", "ar", "ast", "az", "ba", "be", "bg", "bn", "br", "bs", "ca", "ceb", "cs", "cy",
+        "da", "de", "el", "en", "es", "et", "fa", "ff", "fi", "fr", "fy", "ga", "gd", "gl", "gu",
+        "ha", "he", "hi", "hr", "ht", "hu", "hy", "id", "ig", "ilo", "is", "it", "ja", "jv", "ka",
+        "kk", "km", "kn", "ko", "lb", "lg", "ln", "lo", "lt", "lv", "mg", "mk", "ml", "mn", "mr",
+        "ms", "my", "ne", "nl", "no", "ns", "oc", "or", "pa", "pl", "ps", "pt", "ro", "ru", "sd",
+        "si", "sk", "sl", "so", "sq", "sr", "ss", "su", "sv", "sw", "ta", "th", "tl", "tn", "tr",
+        "uk", "ur", "uz", "vi", "wo", "xh", "yi", "yo", "zh", "zu",
+    ];
+    const MAX_GENERATED_IMAGES: u32 = 4;
+    const MIN_IMAGE_SIDE: u32 = 256;
+    const MAX_IMAGE_SIDE: u32 = 2048;
+
+    const fn model_spec(name: &'static str) -> ModelSpec {
+        ModelSpec {
+            name: Cow::Borrowed(name),
+            max_output_tokens: 2048,
+            max_temperature: 5.0,
+            max_stop_sequences: 0,
+            supports_seed: true,
+            supports_top_k: true,
+            supports_json_mode: false,
+            // Workers AI bills in neurons rather than tokens
+            price: ModelPrice::FREE,
+        }
+    }
 
     pub fn new(secrets: &Secrets) -> Self {
         let mut headers = reqwest::header::HeaderMap::new();
@@ -23,47 +76,231 @@ impl CloudflareApi {
             ))
             .unwrap(),
         );
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()
-            .expect("Failed to build reqwest client");
-
         Self {
-            client,
+            client: ProviderClient::new(Self::NAME, secrets, headers),
             account: secrets.cloudflare_account.clone(),
         }
     }
 
-    pub async fn completion(
+    pub fn circuit(&self) -> Arc<CircuitBreaker> {
+        self.client.circuit()
+    }
+
+    pub async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, CustomError> {
+        let chat_completion = self
+            .run_completion(&request.model, &Self::chat_body(request, None))
+            .await?;
+        Ok(ChatResponse {
+            provider: Self::NAME.to_string(),
+            model: request.model.clone(),
+            usage: chat_completion.result.usage.as_ref().map(Usage::token_usage),
+            content: chat_completion.result.response,
+            finish_reason: None,
+            safety_ratings: Vec::new(),
+            tool_calls: Vec::new(),
+            cost_usd: None,
+            cached: false,
+        })
+    }
+
+    pub async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream, CustomError> {
+        let response = self
+            .run(&request.model, &Self::chat_body(request, Some(true)))
+            .await?;
+        Ok(SseUtils::data_events(response)
+            .try_take_while(|data| future::ready(Ok(data != SseUtils::DONE)))
+            .and_then(|data| async move {
+                let chunk: CompletionResultData = serde_json::from_str(&data)?;
+                // Usage is only sent by some models, the gateway estimates it otherwise
+                let deltas: Vec<ChatDelta> = (!chunk.response.is_empty())
+                    .then(|| ChatDelta::Text(chunk.response.clone()))
+                    .into_iter()
+                    .chain(chunk.usage.as_ref().map(|usage| ChatDelta::Usage(usage.token_usage())))
+                    .collect();
+                Ok(stream::iter(deltas.into_iter().map(Ok)))
+            })
+            .try_flatten()
+            .boxed())
+    }
+
+    fn chat_body(request: &ChatRequest, stream: Option<bool>) -> RequestBody {
+        let messages = request
+            .messages
+            .iter()
+            .map(|message| Message {
+                role: CloudflareRole::from_chat_role(message.role).name(),
+                content: message.content.clone(),
+            })
+            .collect();
+        RequestBody {
+            messages,
+            max_tokens: request.params.max_tokens,
+            temperature: request.params.temperature,
+            top_p: request.params.top_p,
+            top_k: request.params.top_k,
+            seed: request.params.seed,
+            stream,
+        }
+    }
+
+    async fn run_completion(
         &self,
-        model: CloudflareModel,
-        prompt: Prompt,
-        message: &str,
-    ) -> Result<String, CustomError> {
-        let messages = vec![
-            Message {
-                role: CloudflareRole::System.name(),
-                content: prompt.prompt(),
-            },
-            Message {
-                role: CloudflareRole::User.name(),
-                content: message.to_string(),
-            },
-        ];
-
-        let request = RequestBody { messages };
-
-        log::info!("Cloudflare payload: {:?}", serde_json::to_string(&request));
+        model: &str,
+        request: &RequestBody,
+    ) -> Result<CloudflareResponseBody, CustomError> {
+        let chat_completion: CloudflareResponseBody = self.run(model, request).await?.json().await?;
+        log::info!("Chat completion: {:?}", chat_completion);
+        Ok(chat_completion)
+    }
+
+    async fn run<T: Serialize>(
+        &self,
+        model: &str,
+        request: &T,
+    ) -> Result<reqwest::Response, CustomError> {
+        log::info!("Cloudflare payload: {:?}", serde_json::to_string(request));
 
+        let url = Self::API_URL
+            .replace("{account}", self.account.as_str())
+            .replace("{model}", model);
+        let response: reqwest::Response =
+            self.client.send(self.client.post(url).json(request)).await?;
+
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            let code = response.status().as_u16();
+            if let Ok(text) = response.text().await {
+                log::error!("Error response: {}", text);
+            }
+            Err(CustomError::NonSuccessfulResponse(code))
+        }
+    }
+
+    pub async fn image_classification(&self, image: Vec<u8>) -> Result<Vec<ImageLabel>, CustomError> {
+        let classification: ImageApiResponse<ImageLabel> = self
+            .run_binary(CloudflareModel::Resnet50, image)
+            .await?
+            .json()
+            .await?;
+        Ok(classification.result)
+    }
+
+    pub async fn object_detection(&self, image: Vec<u8>) -> Result<Vec<DetectedObject>, CustomError> {
+        let detection: ImageApiResponse<DetectedObject> = self
+            .run_binary(CloudflareModel::DetrResnet50, image)
+            .await?
+            .json()
+            .await?;
+        Ok(detection.result)
+    }
+
+    // SDXL answers with a single PNG, more images take more calls
+    pub async fn image_generation(
+        &self,
+        request: &ImageGenerationRequest,
+    ) -> Result<(String, Vec<GeneratedImageData>), CustomError> {
+        let model = CloudflareModel::StableDiffusionXl.name();
+        if request.model.as_deref().is_some_and(|name| name != model) {
+            return Err(CustomError::UnknownModel(request.model.clone().unwrap_or_default()));
+        }
+        request.validate(Self::MAX_GENERATED_IMAGES)?;
+        let (width, height) = request.dimensions()?;
+        if !(Self::MIN_IMAGE_SIDE..=Self::MAX_IMAGE_SIDE).contains(&width)
+            || !(Self::MIN_IMAGE_SIDE..=Self::MAX_IMAGE_SIDE).contains(&height)
+        {
+            return Err(CustomError::InvalidRequest(format!(
+                "width and height must be between {} and {}",
+                Self::MIN_IMAGE_SIDE,
+                Self::MAX_IMAGE_SIDE
+            )));
+        }
+        let body = ImageGenerationBody {
+            prompt: request.prompt.clone(),
+            width,
+            height,
+        };
+        let mut images = Vec::new();
+        for _ in 0..request.n {
+            let bytes = self.run(model, &body).await?.bytes().await?;
+            images.push(GeneratedImageData {
+                bytes: bytes.to_vec(),
+                revised_prompt: None,
+            });
+        }
+        Ok((model.to_string(), images))
+    }
+
+    pub async fn translation(
+        &self,
+        request: &TranslationRequest,
+    ) -> Result<TranslationResponse, CustomError> {
+        for lang in [&request.source_lang, &request.target_lang] {
+            if !Self::TRANSLATION_LANGUAGES.contains(&lang.as_str()) {
+                return Err(CustomError::UnsupportedCapability(format!(
+                    "m2m100 does not support {}",
+                    lang
+                )));
+            }
+        }
+        let model = CloudflareModel::M2m100.name();
+        let body = TranslationBody {
+            text: request.text.clone(),
+            source_lang: request.source_lang.clone(),
+            target_lang: request.target_lang.clone(),
+        };
+        let translation: TranslationApiResponse = self.run(model, &body).await?.json().await?;
+        Ok(TranslationResponse {
+            provider: Self::NAME.to_string(),
+            model: model.to_string(),
+            source_lang: request.source_lang.clone(),
+            target_lang: request.target_lang.clone(),
+            translated_text: translation.result.translated_text,
+        })
+    }
+
+    // Whisper only times words, they are returned as the segments
+    pub async fn transcription(&self, audio: Vec<u8>) -> Result<Transcription, CustomError> {
+        let transcription: WhisperApiResponse = self
+            .run_binary(CloudflareModel::Whisper, audio)
+            .await?
+            .json()
+            .await?;
+        Ok(Transcription {
+            provider: Self::NAME.to_string(),
+            text: transcription.result.text,
+            language: None,
+            segments: transcription
+                .result
+                .words
+                .into_iter()
+                .map(|word| TranscriptionSegment {
+                    start: word.start,
+                    end: word.end,
+                    text: word.word,
+                })
+                .collect(),
+        })
+    }
+
+    // Image and audio models take the raw file bytes as the request body
+    async fn run_binary(
+        &self,
+        model: CloudflareModel,
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response, CustomError> {
         let url = Self::API_URL
             .replace("{account}", self.account.as_str())
             .replace("{model}", model.name());
-        let response: reqwest::Response = self.client.post(url).json(&request).send().await?;
+        let request = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+            .body(body);
+        let response = self.client.send(request).await?;
 
         if response.status().is_success() {
-            let chat_completion: CloudflareResponseBody = response.json().await?;
-            log::info!("Chat completion: {:?}", chat_completion);
-            Ok(chat_completion.result.response)
+            Ok(response)
         } else {
             let code = response.status().as_u16();
             if let Ok(text) = response.text().await {
@@ -83,7 +320,7 @@ impl CloudflareApi {
             .replace("{account}", self.account.as_str())
             .replace("{model}", model.name());
 
-        let response: reqwest::Response = self.client.post(url).json(&body).send().await?;
+        let response: reqwest::Response = self.client.send(self.client.post(url).json(&body)).await?;
 
         if response.status().is_success() {
             let emdebbings: EmbeddingApiResponse = response.json().await?;
@@ -98,16 +335,78 @@ impl CloudflareApi {
     }
 }
 
+#[async_trait]
+impl LlmProvider for CloudflareApi {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            completion: true,
+            vision: false,
+            embedding: true,
+            streaming: true,
+            tools: false,
+            translation: true,
+        }
+    }
+
+    fn models(&self) -> Vec<ModelSpec> {
+        Self::MODELS.to_vec()
+    }
+
+    fn default_model(&self) -> &str {
+        CloudflareModel::Llama27b.name()
+    }
+
+    fn embedding_model(&self) -> Option<&str> {
+        Some(CloudflareModel::BgeBaseEn.name())
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, CustomError> {
+        self.chat(request).await
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream, CustomError> {
+        self.chat_stream(request).await
+    }
+
+    async fn embedding(
+        &self,
+        body: &EmbeddintBodyRequest,
+    ) -> Result<Vec<Vec<f64>>, CustomError> {
+        self.embedding(body).await
+    }
+
+    async fn translation(
+        &self,
+        request: &TranslationRequest,
+    ) -> Result<TranslationResponse, CustomError> {
+        self.translation(request).await
+    }
+}
+
 pub enum CloudflareModel {
     Llama27b,
     BgeBaseEn,
+    Resnet50,
+    DetrResnet50,
+    Whisper,
+    StableDiffusionXl,
+    M2m100,
 }
 
 impl CloudflareModel {
-    fn name(&self) -> &str {
+    pub fn name(&self) -> &'static str {
         match self {
-            CloudflareModel::Llama27b => "meta/llama-2-7b-chat-int8",
-            CloudflareModel::BgeBaseEn => "baai/bge-base-en-v1.5",
+            CloudflareModel::Llama27b => "@cf/meta/llama-2-7b-chat-int8",
+            CloudflareModel::BgeBaseEn => "@cf/baai/bge-base-en-v1.5",
+            CloudflareModel::Resnet50 => "@cf/microsoft/resnet-50",
+            CloudflareModel::DetrResnet50 => "@cf/facebook/detr-resnet-50",
+            CloudflareModel::Whisper => "@cf/openai/whisper",
+            CloudflareModel::StableDiffusionXl => "@cf/stabilityai/stable-diffusion-xl-base-1.0",
+            CloudflareModel::M2m100 => "@cf/meta/m2m100-1.2b",
         }
     }
 }
@@ -115,13 +414,23 @@ impl CloudflareModel {
 enum CloudflareRole {
     User,
     System,
+    Assistant,
 }
 
 impl CloudflareRole {
+    fn from_chat_role(role: ChatRole) -> Self {
+        match role {
+            ChatRole::System => CloudflareRole::System,
+            ChatRole::User | ChatRole::Tool => CloudflareRole::User,
+            ChatRole::Assistant => CloudflareRole::Assistant,
+        }
+    }
+
     fn name(&self) -> String {
         match self {
             CloudflareRole::User => "user".to_string(),
             CloudflareRole::System => "system".to_string(),
+            CloudflareRole::Assistant => "assistant".to_string(),
         }
     }
 }
@@ -129,6 +438,32 @@ impl CloudflareRole {
 #[derive(Serialize, Deserialize, Debug)]
 struct RequestBody {
     messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+}
+
+#[derive(Serialize, Debug)]
+struct TranslationBody {
+    text: String,
+    source_lang: String,
+    target_lang: String,
+}
+
+#[derive(Serialize, Debug)]
+struct ImageGenerationBody {
+    prompt: String,
+    width: u32,
+    height: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -147,7 +482,27 @@ struct CloudflareResponseBody {
 
 #[derive(Serialize, Deserialize, Debug)]
 struct CompletionResultData {
+    // Missing from the last streamed chunk, which only carries the usage
+    #[serde(default)]
     response: String,
+    usage: Option<Usage>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Usage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    total_tokens: u32,
+}
+
+impl Usage {
+    fn token_usage(&self) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens: self.prompt_tokens,
+            completion_tokens: self.completion_tokens,
+            total_tokens: self.total_tokens,
+        }
+    }
 }
 
 
@@ -165,3 +520,62 @@ pub struct EmbeddingResultData {
     data: Vec<Vec<f64>>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+struct ImageApiResponse<T> {
+    result: Vec<T>,
+    success: bool,
+    errors: Vec<String>,
+    messages: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImageLabel {
+    pub label: String,
+    pub score: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DetectedObject {
+    pub label: String,
+    pub score: f32,
+    #[serde(rename = "box")]
+    pub bounding_box: BoundingBox,
+}
+
+// Corners in pixels of the uploaded image
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BoundingBox {
+    pub xmin: f32,
+    pub ymin: f32,
+    pub xmax: f32,
+    pub ymax: f32,
+}
+
+#[derive(Deserialize, Debug)]
+struct WhisperApiResponse {
+    result: WhisperResult,
+}
+
+#[derive(Deserialize, Debug)]
+struct WhisperResult {
+    text: String,
+    #[serde(default)]
+    words: Vec<WhisperWord>,
+}
+
+#[derive(Deserialize, Debug)]
+struct WhisperWord {
+    word: String,
+    start: f64,
+    end: f64,
+}
+
+#[derive(Deserialize, Debug)]
+struct TranslationApiResponse {
+    result: TranslationResult,
+}
+
+#[derive(Deserialize, Debug)]
+struct TranslationResult {
+    translated_text: String,
+}
@@ -1,97 +1,378 @@
+use std::{borrow::Cow, sync::Arc};
+
+use async_trait::async_trait;
+use futures_util::{stream, StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
 
 use crate::{
-    models::custom_error::CustomError,
+    api::{
+        circuit_breaker::CircuitBreaker,
+        llm_provider::{
+            ChatDelta, ChatRequest, ChatResponse, ChatStream, LlmProvider, ProviderCapabilities,
+            TokenUsage,
+        },
+        model_catalog::{ModelPrice, ModelSpec},
+        provider_client::ProviderClient,
+    },
+    models::{
+        chat_message::{ChatMessage, ChatRole},
+        custom_error::CustomError,
+        safety::{HarmProbability, SafetyRating, SafetySetting},
+        sampling_params::SamplingParams,
+        tool::{FunctionDefinition, ToolCall, ToolChoice, ToolChoiceMode},
+    },
     repository::{prompt_provider::Prompt, secrets::Secrets},
+    utils::sse_utils::SseUtils,
 };
 
 pub struct GeminiApi {
-    client: reqwest::Client,
+    client: ProviderClient,
     key: (String, String),
+    safety_settings: Vec<SafetySetting>,
 }
 
 impl GeminiApi {
+    pub const NAME: &'static str = "gemini";
     const API_URL: &'static str =
         "https://generativelanguage.googleapis.com/v1beta/models/{model}:streamGenerateContent";
+    const MODELS: [ModelSpec; 4] = [
+        Self::model_spec("gemini-pro", 8192, 1.0, false, ModelPrice::new(0.5, 1.5)),
+        Self::model_spec("gemini-pro-vision", 2048, 1.0, false, ModelPrice::new(0.5, 1.5)),
+        Self::model_spec("gemini-1.5-pro-latest", 8192, 2.0, true, ModelPrice::new(3.5, 10.5)),
+        Self::model_spec("gemini-1.5-flash-latest", 8192, 2.0, true, ModelPrice::new(0.35, 1.05)),
+    ];
+    // Models accepting `systemInstruction`, the 1.0 models reject it
+    const SYSTEM_INSTRUCTION_MODELS: [&'static str; 2] =
+        ["gemini-1.5-pro-latest", "gemini-1.5-flash-latest"];
+    const COMPLETION_TEMPERATURE: f32 = 1.0;
+
+    const fn model_spec(
+        name: &'static str,
+        max_output_tokens: u32,
+        max_temperature: f32,
+        supports_json_mode: bool,
+        price: ModelPrice,
+    ) -> ModelSpec {
+        ModelSpec {
+            name: Cow::Borrowed(name),
+            max_output_tokens,
+            max_temperature,
+            max_stop_sequences: 5,
+            supports_seed: false,
+            supports_top_k: true,
+            supports_json_mode,
+            price,
+        }
+    }
 
     pub fn new(secrets: &Secrets) -> Self {
-        let client = reqwest::Client::builder()
-            .build()
-            .expect("Failed to build reqwest client");
+        let client = ProviderClient::new(Self::NAME, secrets, reqwest::header::HeaderMap::new());
         let key = ("key".to_string(), secrets.google_ai_studio_api_key.clone());
-        Self { client, key }
+        Self {
+            client,
+            key,
+            safety_settings: secrets.gemini_safety_settings.clone(),
+        }
     }
 
-    pub async fn completion(&self, prompt: Prompt, message: &str) -> Result<String, CustomError> {
+    pub fn circuit(&self) -> Arc<CircuitBreaker> {
+        self.client.circuit()
+    }
+
+    pub async fn completion(&self, prompt: Prompt, message: &str) -> Result<ChatResponse, CustomError> {
         let model = GeminiModel::Text;
-        let role = GeminiRole::User;
-        let model_message = format!("{} . \n {}", prompt.prompt(), message);
+        let messages = [
+            ChatMessage::new(ChatRole::System, prompt.prompt()),
+            ChatMessage::new(ChatRole::User, message.to_string()),
+        ];
+        let (system_instruction, contents) = Self::contents(
+            &messages,
+            Self::SYSTEM_INSTRUCTION_MODELS.contains(&model.name()),
+        )?;
         let request = GeminiRequest {
-            contents: vec![Content {
-                role: role.to_string(),
-                parts: vec![Part::TextPart {
-                    text: model_message.to_string(),
-                }],
-            }],
+            system_instruction,
+            contents,
+            generation_config: GenerationConfig::new(
+                &SamplingParams {
+                    temperature: Some(Self::COMPLETION_TEMPERATURE),
+                    ..SamplingParams::default()
+                },
+                false,
+            ),
+            tools: None,
+            tool_config: None,
+            safety_settings: self.safety_settings.clone(),
         };
-        let url = Self::API_URL.replace("{model}", &model.to_string());
-        let response = self
-            .client
-            .post(&url)
-            .query(&[self.key.clone()])
-            .json(&request)
-            .send()
-            .await?;
-
-        if response.status().is_success() {
-            let chat_completion: Vec<GeminiResponse> = response.json().await?;
-            Ok(chat_completion.iter()
-            .flat_map(|completion| completion.combine_text_parts())
-            .collect::<Vec<String>>()
-            .join(" "))
-        } else {
-            let code = response.status().as_u16();
-            if let Ok(text) = response.text().await {
-                log::error!("Error response: {}", text);
-            }
-            Err(CustomError::NonSuccessfulResponse(code))
-        }
+        let chat_completion = self.generate_content(model.name(), &request).await?;
+        Ok(Self::chat_response(model.name(), &chat_completion))
     }
 
-    pub async fn visual(&self, prompt: Prompt, base64_image: &str) -> Result<String, CustomError> {
+    pub async fn visual(&self, prompt: Prompt, base64_image: &str) -> Result<ChatResponse, CustomError> {
         let model = GeminiModel::Vision;
         let role = GeminiRole::User;
         let request = GeminiRequest {
+            system_instruction: None,
             contents: vec![Content {
                 role: role.to_string(),
                 parts: vec![
-                    Part::TextPart {
+                    Part::Text {
                         text: prompt.prompt(),
                     },
-                    Part::DataPart {
+                    Part::Data {
                     inline_data: InlineData {
                         mime_type: "image/jpeg".to_string(),
                         data: base64_image.to_string(),
                     },
                 }],
             }],
+            generation_config: None,
+            tools: None,
+            tool_config: None,
+            safety_settings: self.safety_settings.clone(),
+        };
+        let chat_completion = self.generate_content(model.name(), &request).await?;
+        Ok(Self::chat_response(model.name(), &chat_completion))
+    }
+
+    pub async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, CustomError> {
+        let gemini_request = self.chat_request(request)?;
+        let chat_completion = self.generate_content(&request.model, &gemini_request).await?;
+        Ok(Self::chat_response(&request.model, &chat_completion))
+    }
+
+    fn chat_response(model: &str, chat_completion: &[GeminiResponse]) -> ChatResponse {
+        let tool_calls: Vec<ToolCall> = chat_completion
+            .iter()
+            .flat_map(|completion| completion.tool_calls())
+            .collect();
+        // Gemini finishes function calls with STOP, OpenAI clients expect `tool_calls`
+        let finish_reason = if tool_calls.is_empty() {
+            chat_completion
+                .iter()
+                .flat_map(|completion| &completion.candidates)
+                .last()
+                .and_then(Candidate::finish_reason)
+        } else {
+            Some("tool_calls".to_string())
         };
-        let url = Self::API_URL.replace("{model}", &model.to_string());
-        let response = reqwest::Client::new()
-            .post(&url)
-            .query(&[self.key.clone()])
-            .json(&request)
-            .send()
+        ChatResponse {
+            provider: Self::NAME.to_string(),
+            model: model.to_string(),
+            content: Self::combine_text(chat_completion),
+            tool_calls,
+            finish_reason,
+            safety_ratings: chat_completion
+                .iter()
+                .rev()
+                .flat_map(|completion| &completion.candidates)
+                .find(|candidate| !candidate.safety_ratings.is_empty())
+                .map(|candidate| candidate.safety_ratings.clone())
+                .unwrap_or_default(),
+            // Counts are cumulative, the last chunk carries the totals
+            usage: chat_completion
+                .iter()
+                .rev()
+                .find_map(|completion| completion.usage_metadata.as_ref())
+                .map(UsageMetadata::token_usage),
+            cost_usd: None,
+            cached: false,
+        }
+    }
+
+    pub async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream, CustomError> {
+        let gemini_request = self.chat_request(request)?;
+        let response = self
+            .send(
+                &request.model,
+                &gemini_request,
+                ("alt".to_string(), "sse".to_string()),
+            )
             .await?;
+        Ok(SseUtils::data_events(response)
+            .and_then(|data| async move {
+                let completion: GeminiResponse = serde_json::from_str(&data)?;
+                completion.blocked()?;
+                // Every chunk carries the usage so far, the last one counts
+                let deltas: Vec<ChatDelta> = completion
+                    .combine_text_parts()
+                    .filter(|text| !text.is_empty())
+                    .map(ChatDelta::Text)
+                    .into_iter()
+                    .chain(completion.usage_metadata.as_ref().map(|usage| ChatDelta::Usage(usage.token_usage())))
+                    .collect();
+                Ok(stream::iter(deltas.into_iter().map(Ok)))
+            })
+            .try_flatten()
+            .boxed())
+    }
 
-        if response.status().is_success() {
-            let text_text = &response.text().await?;
-            log::debug!("text_text: {}", text_text);
-            let chat_completion: Vec<GeminiResponse> = serde_json::from_str(text_text)?;
-            Ok(chat_completion.iter()
+    fn chat_request(&self, request: &ChatRequest) -> Result<GeminiRequest, CustomError> {
+        let safety_settings = if request.safety_settings.is_empty() {
+            &self.safety_settings
+        } else {
+            &request.safety_settings
+        };
+        let system_instruction = Self::SYSTEM_INSTRUCTION_MODELS.contains(&request.model.as_str());
+        // Schemas are checked by the gateway, the API is only asked for JSON
+        let json_mode = request.response_format.as_ref().is_some_and(|format| format.is_json())
+            && self
+                .model(&request.model)
+                .is_ok_and(|model| model.supports_json_mode);
+        let (system, contents) = Self::contents(&request.messages, system_instruction)?;
+        Ok(GeminiRequest {
+            system_instruction: system,
+            contents,
+            generation_config: GenerationConfig::new(&request.params, json_mode),
+            tools: (!request.tools.is_empty()).then(|| {
+                vec![GeminiTool {
+                    function_declarations: request
+                        .tools
+                        .iter()
+                        .map(|tool| tool.function.clone())
+                        .collect(),
+                }]
+            }),
+            tool_config: request.tool_choice.as_ref().map(ToolConfig::new),
+            safety_settings: safety_settings.clone(),
+        })
+    }
+
+    // Gemini expects alternating user/model turns and has no system role, so system
+    // messages become the system instruction, or are folded into the opening user turn
+    // on models without one, and repeated roles are merged
+    fn contents(
+        messages: &[ChatMessage],
+        system_instruction: bool,
+    ) -> Result<(Option<Content>, Vec<Content>), CustomError> {
+        let system = messages
+            .iter()
+            .filter(|message| message.role == ChatRole::System)
+            .map(|message| message.content.as_str())
+            .collect::<Vec<&str>>()
+            .join("\n");
+        let mut contents: Vec<Content> = Vec::new();
+        for message in messages
+            .iter()
+            .filter(|message| message.role != ChatRole::System)
+        {
+            let role = GeminiRole::from_chat_role(message.role).to_string();
+            for part in Self::parts(message, messages)? {
+                match contents.last_mut() {
+                    Some(content) if content.role == role => content.parts.push(part),
+                    _ => contents.push(Content {
+                        role: role.clone(),
+                        parts: vec![part],
+                    }),
+                }
+            }
+        }
+        if system.is_empty() {
+            return Ok((None, contents));
+        }
+        if system_instruction {
+            return Ok((Some(Content::system(system)), contents));
+        }
+        let user = GeminiRole::User.to_string();
+        match contents.first_mut() {
+            Some(content) if content.role == user => {
+                content.parts.insert(0, Part::Text { text: system })
+            }
+            _ => contents.insert(
+                0,
+                Content {
+                    role: user,
+                    parts: vec![Part::Text { text: system }],
+                },
+            ),
+        }
+        Ok((None, contents))
+    }
+
+    // Tool calls become functionCall parts and tool results functionResponse parts
+    fn parts(message: &ChatMessage, history: &[ChatMessage]) -> Result<Vec<Part>, CustomError> {
+        if message.role == ChatRole::Tool {
+            let name = message.tool_name(history).ok_or_else(|| {
+                CustomError::InvalidRequest(format!(
+                    "tool message {} does not answer a known tool call",
+                    message.tool_call_id.as_deref().unwrap_or_default()
+                ))
+            })?;
+            return Ok(vec![Part::FunctionResponse {
+                function_response: FunctionResponse {
+                    name: name.to_string(),
+                    response: FunctionResponse::content(&message.content),
+                },
+            }]);
+        }
+
+        let mut parts = Vec::new();
+        if !message.content.is_empty() || message.tool_calls.is_empty() {
+            parts.push(Part::Text {
+                text: message.content.clone(),
+            });
+        }
+        for call in &message.tool_calls {
+            let args = serde_json::from_str(&call.function.arguments).map_err(|e| {
+                CustomError::InvalidRequest(format!(
+                    "arguments of tool call {} are not JSON: {}",
+                    call.id, e
+                ))
+            })?;
+            parts.push(Part::FunctionCall {
+                function_call: FunctionCall {
+                    name: call.function.name.clone(),
+                    args,
+                },
+            });
+        }
+        Ok(parts)
+    }
+
+    fn combine_text(chat_completion: &[GeminiResponse]) -> String {
+        chat_completion
+            .iter()
             .flat_map(|completion| completion.combine_text_parts())
             .collect::<Vec<String>>()
-            .join(" "))
+            .join(" ")
+    }
+
+    async fn generate_content(
+        &self,
+        model: &str,
+        request: &GeminiRequest,
+    ) -> Result<Vec<GeminiResponse>, CustomError> {
+        let response = self
+            .send(model, request, ("alt".to_string(), "json".to_string()))
+            .await?;
+        let text_text = &response.text().await?;
+        log::debug!("text_text: {}", text_text);
+        let completions: Vec<GeminiResponse> = serde_json::from_str(text_text)?;
+        for completion in &completions {
+            completion.blocked()?;
+        }
+        Ok(completions)
+    }
+
+    async fn send(
+        &self,
+        model: &str,
+        request: &GeminiRequest,
+        alt: (String, String),
+    ) -> Result<reqwest::Response, CustomError> {
+        let url = Self::API_URL.replace("{model}", model);
+        let response = self
+            .client
+            .send(
+                self.client
+                    .post(&url)
+                    .query(&[self.key.clone(), alt])
+                    .json(request),
+            )
+            .await?;
+
+        if response.status().is_success() {
+            Ok(response)
         } else {
             let code = response.status().as_u16();
             if let Ok(text) = response.text().await {
@@ -99,7 +380,49 @@ impl GeminiApi {
             }
             Err(CustomError::NonSuccessfulResponse(code))
         }
-    } 
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GeminiApi {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            completion: true,
+            vision: true,
+            embedding: false,
+            streaming: true,
+            tools: true,
+            translation: false,
+        }
+    }
+
+    fn models(&self) -> Vec<ModelSpec> {
+        Self::MODELS.to_vec()
+    }
+
+    fn default_model(&self) -> &str {
+        GeminiModel::Text.name()
+    }
+
+    fn vision_model(&self) -> Option<&str> {
+        Some(GeminiModel::Vision.name())
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, CustomError> {
+        self.chat(request).await
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream, CustomError> {
+        self.chat_stream(request).await
+    }
+
+    async fn visual(&self, prompt: Prompt, base64_image: &str) -> Result<ChatResponse, CustomError> {
+        self.visual(prompt, base64_image).await
+    }
 }
 
 enum GeminiModel {
@@ -108,25 +431,34 @@ enum GeminiModel {
 }
 
 impl GeminiModel {
-    pub fn to_string(&self) -> String {
+    pub fn name(&self) -> &'static str {
         match self {
             GeminiModel::Text => "gemini-pro",
             GeminiModel::Vision => "gemini-pro-vision",
         }
-        .to_string()
     }
 }
 
 enum GeminiRole {
     Model,
     User,
+    Function,
 }
 
 impl GeminiRole {
+    fn from_chat_role(role: ChatRole) -> Self {
+        match role {
+            ChatRole::Assistant => GeminiRole::Model,
+            ChatRole::System | ChatRole::User => GeminiRole::User,
+            ChatRole::Tool => GeminiRole::Function,
+        }
+    }
+
     pub fn to_string(&self) -> String {
         match self {
             GeminiRole::Model => "model",
             GeminiRole::User => "user",
+            GeminiRole::Function => "function",
         }
         .to_string()
     }
@@ -134,20 +466,153 @@ impl GeminiRole {
 
 #[derive(Serialize, Deserialize, Debug)]
 struct GeminiRequest {
+    #[serde(rename = "systemInstruction", skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content>,
     contents: Vec<Content>,
+    #[serde(rename = "generationConfig", skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GenerationConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<GeminiTool>>,
+    #[serde(rename = "toolConfig", skip_serializing_if = "Option::is_none")]
+    tool_config: Option<ToolConfig>,
+    #[serde(rename = "safetySettings", skip_serializing_if = "Vec::is_empty")]
+    safety_settings: Vec<SafetySetting>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GeminiTool {
+    function_declarations: Vec<FunctionDefinition>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ToolConfig {
+    function_calling_config: FunctionCallingConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct FunctionCallingConfig {
+    mode: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allowed_function_names: Option<Vec<String>>,
+}
+
+impl ToolConfig {
+    fn new(choice: &ToolChoice) -> Self {
+        let (mode, allowed_function_names) = match choice {
+            ToolChoice::Mode(ToolChoiceMode::None) => ("NONE", None),
+            ToolChoice::Mode(ToolChoiceMode::Auto) => ("AUTO", None),
+            ToolChoice::Mode(ToolChoiceMode::Required) => ("ANY", None),
+            ToolChoice::Function { function, .. } => ("ANY", Some(vec![function.name.clone()])),
+        };
+        Self {
+            function_calling_config: FunctionCallingConfig {
+                mode: mode.to_string(),
+                allowed_function_names,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<String>,
+}
+
+impl GenerationConfig {
+    fn new(params: &SamplingParams, json_mode: bool) -> Option<Self> {
+        if params.temperature.is_none()
+            && params.top_p.is_none()
+            && params.top_k.is_none()
+            && params.max_tokens.is_none()
+            && params.stop.is_none()
+            && !json_mode
+        {
+            return None;
+        }
+        Some(Self {
+            temperature: params.temperature,
+            top_p: params.top_p,
+            top_k: params.top_k,
+            max_output_tokens: params.max_tokens,
+            stop_sequences: params.stop.clone(),
+            response_mime_type: json_mode.then(|| "application/json".to_string()),
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct Content {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     role: String,
     parts: Vec<Part>,
 }
 
+impl Content {
+    // System instructions are sent without a role
+    fn system(text: String) -> Self {
+        Self {
+            role: String::new(),
+            parts: vec![Part::Text { text }],
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(untagged)]
 enum Part {
-    TextPart { text: String },
-    DataPart { inline_data: InlineData },
+    Text {
+        text: String,
+    },
+    Data {
+        inline_data: InlineData,
+    },
+    FunctionCall {
+        #[serde(rename = "functionCall")]
+        function_call: FunctionCall,
+    },
+    FunctionResponse {
+        #[serde(rename = "functionResponse")]
+        function_response: FunctionResponse,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct FunctionCall {
+    name: String,
+    #[serde(default)]
+    args: JsonValue,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct FunctionResponse {
+    name: String,
+    response: JsonValue,
+}
+
+impl FunctionResponse {
+    // Gemini takes an object, other JSON values and plain text are wrapped
+    fn content(content: &str) -> JsonValue {
+        match serde_json::from_str::<JsonValue>(content) {
+            Ok(value) if value.is_object() => value,
+            Ok(value) => json!({ "content": value }),
+            Err(_) => json!({ "content": content }),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -163,20 +628,99 @@ struct GeminiRootResponse {
 
 #[derive(Serialize, Deserialize, Debug)]
 struct GeminiResponse {
+    // Missing when the prompt is blocked
+    #[serde(default)]
     candidates: Vec<Candidate>,
     #[serde(rename = "promptFeedback")]
     prompt_feedback: Option<PromptFeedback>,
+    #[serde(rename = "usageMetadata")]
+    usage_metadata: Option<UsageMetadata>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct UsageMetadata {
+    prompt_token_count: u32,
+    candidates_token_count: u32,
+    total_token_count: u32,
+}
+
+impl UsageMetadata {
+    fn token_usage(&self) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens: self.prompt_token_count,
+            completion_tokens: self.candidates_token_count,
+            total_tokens: self.total_token_count,
+        }
+    }
 }
 
 impl GeminiResponse {
+    // Blocked prompts come back without candidates, blocked answers with a safety finish reason
+    fn blocked(&self) -> Result<(), CustomError> {
+        if let Some(PromptFeedback {
+            block_reason: Some(reason),
+            safety_ratings,
+        }) = &self.prompt_feedback
+        {
+            return Err(CustomError::ContentBlocked(format!(
+                "prompt blocked for {}{}",
+                reason,
+                Self::flagged(safety_ratings)
+            )));
+        }
+        for candidate in &self.candidates {
+            if let Some(
+                reason @ ("SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII"),
+            ) = candidate.finish_reason.as_deref()
+            {
+                return Err(CustomError::ContentBlocked(format!(
+                    "answer blocked for {}{}",
+                    reason,
+                    Self::flagged(&candidate.safety_ratings)
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn flagged(ratings: &[SafetyRating]) -> String {
+        let flagged: Vec<String> = ratings
+            .iter()
+            .filter(|rating| {
+                rating.blocked
+                    || matches!(rating.probability, HarmProbability::Medium | HarmProbability::High)
+            })
+            .map(|rating| format!("{:?} {:?}", rating.category, rating.probability))
+            .collect();
+        if flagged.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", flagged.join(", "))
+        }
+    }
+
+    fn tool_calls(&self) -> Vec<ToolCall> {
+        self.candidates
+            .iter()
+            .flat_map(Candidate::parts)
+            .filter_map(|part| match part {
+                Part::FunctionCall { function_call } => {
+                    Some(ToolCall::new(&function_call.name, &function_call.args))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     fn combine_text_parts(&self) -> Option<String> {
         let text = self
             .candidates
             .iter()
-            .flat_map(|candidate| &candidate.content.parts)
+            .flat_map(Candidate::parts)
             .filter_map(|part| match part {
-                Part::TextPart { text } => Some(text.clone()),
-                Part::DataPart { .. } => None,
+                Part::Text { text } => Some(text.clone()),
+                _ => None,
             })
             .collect::<Vec<String>>()
             .join(" ");
@@ -190,28 +734,47 @@ impl GeminiResponse {
 
 #[derive(Serialize, Deserialize, Debug)]
 struct Candidate {
-    content: ContentData,
+    // Missing on blocked answers and on chunks before the last one
+    content: Option<ContentData>,
     #[serde(rename = "finishReason")]
-    finish_reason: String,
-    index: i32,
-    #[serde(rename = "safetyRatings")]
+    finish_reason: Option<String>,
+    index: Option<i32>,
+    #[serde(rename = "safetyRatings", default)]
     safety_ratings: Vec<SafetyRating>,
 }
 
+impl Candidate {
+    fn parts(&self) -> &[Part] {
+        self.content
+            .as_ref()
+            .map(|content| content.parts.as_slice())
+            .unwrap_or_default()
+    }
+
+    // Maps Gemini finish reasons onto the OpenAI vocabulary
+    fn finish_reason(&self) -> Option<String> {
+        self.finish_reason.as_deref().map(|reason| {
+            match reason {
+                "STOP" => "stop",
+                "MAX_TOKENS" => "length",
+                "SAFETY" | "RECITATION" => "content_filter",
+                other => other,
+            }
+            .to_lowercase()
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct ContentData {
     parts: Vec<Part>,
     role: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct SafetyRating {
-    category: String,
-    probability: String,
-}
-
 #[derive(Serialize, Deserialize, Debug)]
 struct PromptFeedback {
-    #[serde(rename = "safetyRatings")]
+    #[serde(rename = "blockReason")]
+    block_reason: Option<String>,
+    #[serde(rename = "safetyRatings", default)]
     safety_ratings: Vec<SafetyRating>,
 }
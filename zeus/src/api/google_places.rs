@@ -1,25 +1,30 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, sync::Arc};
 
-use crate::{models::custom_error::CustomError, repository::secrets::Secrets};
+use crate::{
+    api::{circuit_breaker::CircuitBreaker, provider_client::ProviderClient}, models::custom_error::CustomError, repository::secrets::Secrets,
+};
 use serde::{Deserialize, Serialize};
 
 pub struct GooglePlacesApi {
-    client: reqwest::Client,
+    client: ProviderClient,
     key: (String, String),
 }
 
 impl GooglePlacesApi {
+    pub const NAME: &'static str = "google_places";
     const API_URL: &'static str = "https://maps.googleapis.com/maps/api/geocode/json";
     const LATP_LNG: &'static str = "latlng";
 
     pub fn new(secrets: &Secrets) -> Self {
-        let client = reqwest::Client::builder()
-            .build()
-            .expect("Failed to build reqwest client");
+        let client = ProviderClient::new(Self::NAME, secrets, reqwest::header::HeaderMap::new());
         let key = ("key".to_string(), secrets.google_vision_api_key.clone());
         Self { client, key }
     }
 
+    pub fn circuit(&self) -> Arc<CircuitBreaker> {
+        self.client.circuit()
+    }
+
     pub async fn geocoding(
         &self,
         location: GoogleGeocodeApiRequest,
@@ -27,9 +32,11 @@ impl GooglePlacesApi {
         let latlng = format!("{},{}", location.lat, location.lng);
         let res = self
             .client
-            .get(Self::API_URL)
-            .query(&[self.key.clone(), (Self::LATP_LNG.to_string(), latlng)])
-            .send()
+            .send(
+                self.client
+                    .get(Self::API_URL)
+                    .query(&[self.key.clone(), (Self::LATP_LNG.to_string(), latlng)]),
+            )
             .await?;
 
         if res.status().is_success() {
@@ -1,25 +1,30 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{models::custom_error::CustomError, repository::secrets::Secrets};
+use crate::{
+    api::{circuit_breaker::CircuitBreaker, provider_client::ProviderClient}, models::custom_error::CustomError, repository::secrets::Secrets,
+};
 
 pub struct GoogleVisionApi {
-    client: reqwest::Client,
+    client: ProviderClient,
     key: (String, String),
 }
 
 impl GoogleVisionApi {
+    pub const NAME: &'static str = "google_vision";
     const API_URL: &'static str = "https://vision.googleapis.com/v1/images:annotate";
 
     pub fn new(secrets: &Secrets) -> Self {
-        let client = reqwest::Client::builder()
-            .build()
-            .expect("Failed to build reqwest client");
+        let client = ProviderClient::new(Self::NAME, secrets, reqwest::header::HeaderMap::new());
         let key = ("key".to_string(), secrets.google_vision_api_key.clone());
         Self { client, key }
     }
 
+    pub fn circuit(&self) -> Arc<CircuitBreaker> {
+        self.client.circuit()
+    }
+
     pub async fn vision(
         &self,
         base64_image: &str,
@@ -42,10 +47,12 @@ impl GoogleVisionApi {
 
         let res = self
             .client
-            .post(Self::API_URL)
-            .query(&[self.key.clone()])
-            .json(&body)
-            .send()
+            .send(
+                self.client
+                    .post(Self::API_URL)
+                    .query(&[self.key.clone()])
+                    .json(&body),
+            )
             .await?;
 
         if res.status().is_success() {
@@ -0,0 +1,177 @@
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::model_catalog::{ModelPrice, ModelSpec},
+    models::{
+        chat_message::{ChatMessage, ChatRole},
+        custom_error::CustomError,
+        embedding_body_request::EmbeddintBodyRequest,
+        response_format::ResponseFormat,
+        safety::{SafetyRating, SafetySetting},
+        sampling_params::SamplingParams,
+        tool::{Tool, ToolCall, ToolChoice},
+        translation::{TranslationRequest, TranslationResponse},
+    },
+    repository::prompt_provider::Prompt,
+};
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct ProviderCapabilities {
+    pub completion: bool,
+    pub vision: bool,
+    pub embedding: bool,
+    pub streaming: bool,
+    pub tools: bool,
+    pub translation: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    pub params: SamplingParams,
+    pub tools: Vec<Tool>,
+    pub tool_choice: Option<ToolChoice>,
+    pub response_format: Option<ResponseFormat>,
+    // Honored by Gemini only, empty keeps the configured defaults
+    pub safety_settings: Vec<SafetySetting>,
+}
+
+impl ChatRequest {
+    // Tool definitions, assistant tool calls or tool results anywhere in the request
+    pub fn uses_tools(&self) -> bool {
+        !self.tools.is_empty()
+            || self.messages.iter().any(|message| {
+                message.role == ChatRole::Tool || !message.tool_calls.is_empty()
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChatResponse {
+    pub provider: String,
+    pub model: String,
+    pub content: String,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+    pub finish_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub safety_ratings: Vec<SafetyRating>,
+    pub usage: Option<TokenUsage>,
+    // Priced by the registry from the model catalog, providers leave it empty
+    pub cost_usd: Option<f64>,
+    // Served from the response cache instead of the provider
+    #[serde(skip)]
+    pub cached: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+// Usage is only known once the provider has sent its last chunk
+#[derive(Debug, Clone)]
+pub enum ChatDelta {
+    Text(String),
+    Usage(TokenUsage),
+}
+
+pub type ChatStream = BoxStream<'static, Result<ChatDelta, CustomError>>;
+
+pub type TextStream = BoxStream<'static, Result<String, CustomError>>;
+
+// A chat stream along with the provider, model and price of the route that accepted it
+pub struct RoutedStream {
+    pub provider: String,
+    pub model: String,
+    pub price: Option<ModelPrice>,
+    pub stream: ChatStream,
+}
+
+// The text of a routed stream, its usage is recorded once the stream is dropped
+pub struct MeteredStream {
+    pub provider: String,
+    pub model: String,
+    pub stream: TextStream,
+}
+
+// Common interface for the LLM vendors, unsupported capabilities fall back to an error
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn capabilities(&self) -> ProviderCapabilities;
+
+    // Chat models served by the provider, used to route and validate requests by model name
+    fn models(&self) -> Vec<ModelSpec>;
+
+    fn model(&self, name: &str) -> Result<ModelSpec, CustomError> {
+        self.models()
+            .into_iter()
+            .find(|model| model.name == name)
+            .ok_or_else(|| CustomError::UnknownModel(name.to_string()))
+    }
+
+    fn default_model(&self) -> &str;
+
+    // Catalog price of a call, unknown for models outside the catalog or without usage
+    fn cost(&self, model: &str, usage: Option<TokenUsage>) -> Option<f64> {
+        self.model(model)
+            .ok()
+            .zip(usage)
+            .map(|(spec, usage)| spec.price.cost(&usage))
+    }
+
+    // Models behind `visual` and `embedding`, recorded with the usage of those calls
+    fn vision_model(&self) -> Option<&str> {
+        None
+    }
+
+    fn embedding_model(&self) -> Option<&str> {
+        None
+    }
+
+    async fn chat(&self, _request: &ChatRequest) -> Result<ChatResponse, CustomError> {
+        Err(CustomError::UnsupportedCapability(format!(
+            "{} does not support chat",
+            self.name()
+        )))
+    }
+
+    async fn chat_stream(&self, _request: &ChatRequest) -> Result<ChatStream, CustomError> {
+        Err(CustomError::UnsupportedCapability(format!(
+            "{} does not support streaming",
+            self.name()
+        )))
+    }
+
+    async fn visual(&self, _prompt: Prompt, _base64_image: &str) -> Result<ChatResponse, CustomError> {
+        Err(CustomError::UnsupportedCapability(format!(
+            "{} does not support vision",
+            self.name()
+        )))
+    }
+
+    async fn embedding(&self, _body: &EmbeddintBodyRequest) -> Result<Vec<Vec<f64>>, CustomError> {
+        Err(CustomError::UnsupportedCapability(format!(
+            "{} does not support embeddings",
+            self.name()
+        )))
+    }
+
+    // Dedicated translation models, language pairs they can't serve are unsupported too
+    async fn translation(
+        &self,
+        _request: &TranslationRequest,
+    ) -> Result<TranslationResponse, CustomError> {
+        Err(CustomError::UnsupportedCapability(format!(
+            "{} does not support translation",
+            self.name()
+        )))
+    }
+}
@@ -2,4 +2,11 @@ pub mod open_ai;
 pub mod google_vision;
 pub mod cloudflare_ai;
 pub mod google_gemini;
-pub mod google_places;
\ No newline at end of file
+pub mod google_places;
+pub mod llm_provider;
+pub mod provider_registry;
+pub mod model_catalog;
+pub mod anthropic;
+pub mod retry_policy;
+pub mod circuit_breaker;
+pub mod provider_client;
\ No newline at end of file
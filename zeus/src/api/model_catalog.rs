@@ -0,0 +1,212 @@
+use std::borrow::Cow;
+
+use serde::Serialize;
+
+use crate::{
+    api::llm_provider::TokenUsage,
+    models::{custom_error::CustomError, sampling_params::SamplingParams},
+};
+
+// Limits of a model offered by a provider, requests are validated against them
+#[derive(Serialize, Debug, Clone)]
+pub struct ModelSpec {
+    pub name: Cow<'static, str>,
+    pub max_output_tokens: u32,
+    pub max_temperature: f32,
+    pub max_stop_sequences: usize,
+    pub supports_seed: bool,
+    pub supports_top_k: bool,
+    // Native JSON output, other models are only instructed to answer in JSON
+    pub supports_json_mode: bool,
+    pub price: ModelPrice,
+}
+
+// USD per million tokens
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl ModelPrice {
+    pub const FREE: ModelPrice = ModelPrice::new(0.0, 0.0);
+
+    pub const fn new(prompt: f64, completion: f64) -> Self {
+        Self { prompt, completion }
+    }
+
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.prompt_tokens as f64 * self.prompt + usage.completion_tokens as f64 * self.completion)
+            / 1_000_000.0
+    }
+}
+
+impl ModelSpec {
+    pub fn validate(&self, params: &SamplingParams) -> Result<(), CustomError> {
+        if let Some(temperature) = params.temperature {
+            if !(0.0..=self.max_temperature).contains(&temperature) {
+                return Err(self.invalid(format!(
+                    "temperature must be between 0 and {}",
+                    self.max_temperature
+                )));
+            }
+        }
+        if let Some(top_p) = params.top_p {
+            if top_p <= 0.0 || top_p > 1.0 {
+                return Err(self.invalid("top_p must be greater than 0 and at most 1".to_string()));
+            }
+        }
+        if let Some(top_k) = params.top_k {
+            if !self.supports_top_k {
+                return Err(self.invalid("top_k is not supported".to_string()));
+            }
+            if top_k == 0 {
+                return Err(self.invalid("top_k must be at least 1".to_string()));
+            }
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            if max_tokens == 0 || max_tokens > self.max_output_tokens {
+                return Err(self.invalid(format!(
+                    "max_tokens must be between 1 and {}",
+                    self.max_output_tokens
+                )));
+            }
+        }
+        if let Some(stop) = &params.stop {
+            if stop.len() > self.max_stop_sequences {
+                return Err(self.invalid(format!(
+                    "at most {} stop sequences are supported",
+                    self.max_stop_sequences
+                )));
+            }
+        }
+        if params.seed.is_some() && !self.supports_seed {
+            return Err(self.invalid("seed is not supported".to_string()));
+        }
+        Ok(())
+    }
+
+    fn invalid(&self, reason: String) -> CustomError {
+        CustomError::InvalidRequest(format!("{}: {}", self.name, reason))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ModelSpec {
+        ModelSpec {
+            name: Cow::Borrowed("test-model"),
+            max_output_tokens: 1024,
+            max_temperature: 1.0,
+            max_stop_sequences: 2,
+            supports_seed: false,
+            supports_top_k: true,
+            supports_json_mode: false,
+            price: ModelPrice::FREE,
+        }
+    }
+
+    fn rejects(spec: &ModelSpec, params: SamplingParams, reason: &str) {
+        match spec.validate(&params) {
+            Err(CustomError::InvalidRequest(message)) => {
+                assert_eq!(message, format!("test-model: {}", reason))
+            }
+            other => panic!("expected {:?} to be rejected, got {:?}", params, other),
+        }
+    }
+
+    #[test]
+    fn accepts_params_within_limits() {
+        let params = SamplingParams {
+            temperature: Some(1.0),
+            top_p: Some(1.0),
+            top_k: Some(40),
+            max_tokens: Some(1024),
+            stop: Some(vec!["a".to_string(), "b".to_string()]),
+            seed: None,
+        };
+        assert!(spec().validate(&params).is_ok());
+        assert!(spec().validate(&SamplingParams::default()).is_ok());
+    }
+
+    #[test]
+    fn max_tokens_must_be_within_the_output_limit() {
+        let reason = "max_tokens must be between 1 and 1024";
+        for max_tokens in [0, 1025] {
+            let params = SamplingParams {
+                max_tokens: Some(max_tokens),
+                ..Default::default()
+            };
+            rejects(&spec(), params, reason);
+        }
+    }
+
+    #[test]
+    fn temperature_must_be_within_the_model_range() {
+        let reason = "temperature must be between 0 and 1";
+        for temperature in [-0.1, 1.5] {
+            let params = SamplingParams {
+                temperature: Some(temperature),
+                ..Default::default()
+            };
+            rejects(&spec(), params, reason);
+        }
+    }
+
+    #[test]
+    fn top_p_must_be_a_probability() {
+        let reason = "top_p must be greater than 0 and at most 1";
+        for top_p in [0.0, 1.1] {
+            let params = SamplingParams {
+                top_p: Some(top_p),
+                ..Default::default()
+            };
+            rejects(&spec(), params, reason);
+        }
+    }
+
+    #[test]
+    fn stop_sequences_are_limited() {
+        let params = SamplingParams {
+            stop: Some(vec!["a".to_string(), "b".to_string(), "c".to_string()]),
+            ..Default::default()
+        };
+        rejects(&spec(), params, "at most 2 stop sequences are supported");
+    }
+
+    #[test]
+    fn seed_requires_support() {
+        let params = SamplingParams {
+            seed: Some(7),
+            ..Default::default()
+        };
+        rejects(&spec(), params.clone(), "seed is not supported");
+
+        let seeded = ModelSpec {
+            supports_seed: true,
+            ..spec()
+        };
+        assert!(seeded.validate(&params).is_ok());
+    }
+
+    #[test]
+    fn top_k_requires_support_and_a_positive_value() {
+        let params = SamplingParams {
+            top_k: Some(0),
+            ..Default::default()
+        };
+        rejects(&spec(), params, "top_k must be at least 1");
+
+        let without_top_k = ModelSpec {
+            supports_top_k: false,
+            ..spec()
+        };
+        let params = SamplingParams {
+            top_k: Some(40),
+            ..Default::default()
+        };
+        rejects(&without_top_k, params, "top_k is not supported");
+    }
+}
@@ -1,67 +1,178 @@
+use std::{borrow::Cow, sync::Arc};
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine};
+use futures_util::{future, stream, StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
 
 use crate::{
-    models::custom_error::CustomError,
-    repository::{prompt_provider::Prompt, secrets::Secrets},
+    api::{
+        circuit_breaker::CircuitBreaker,
+        llm_provider::{
+            ChatDelta, ChatRequest, ChatResponse, ChatStream, LlmProvider, ProviderCapabilities,
+            TokenUsage,
+        },
+        model_catalog::{ModelPrice, ModelSpec},
+        provider_client::ProviderClient,
+    },
+    models::{
+        chat_message::ChatRole,
+        custom_error::CustomError,
+        image_generation::{GeneratedImageData, ImageGenerationRequest},
+        speech_request::{AudioStream, SpeechFormat, SpeechRequest, SpeechVoice},
+        tool::{Tool, ToolCall, ToolChoice},
+        transcription::{Transcription, TranscriptionSegment},
+    },
+    repository::{
+        prompt_provider::Prompt,
+        secrets::{OpenAiCompatibleSecrets, Secrets},
+    },
+    utils::sse_utils::SseUtils,
 };
 
 pub struct OpenAIApi {
-    client: reqwest::Client,
+    client: ProviderClient,
+    name: String,
+    url: String,
+    models: Vec<ModelSpec>,
+    default_model: String,
+    vision_model: Option<String>,
 }
 
 impl OpenAIApi {
-    const API_URL: &'static str = "https://api.openai.com/v1/chat/completions";
+    pub const NAME: &'static str = "openai";
+    const BASE_URL: &'static str = "https://api.openai.com/v1";
+    const CHAT_PATH: &'static str = "/chat/completions";
+    const TRANSCRIPTION_PATH: &'static str = "/audio/transcriptions";
+    const SPEECH_PATH: &'static str = "/audio/speech";
+    const IMAGES_PATH: &'static str = "/images/generations";
     const MAX_TOKENS: u32 = 4096;
+    pub const WHISPER_USD_PER_MINUTE: f64 = 0.006;
+    pub const TTS_USD_PER_MILLION_CHARS: f64 = 15.0;
+    const MODELS: [ModelSpec; 5] = [
+        Self::model_spec("gpt-4-1106-preview", true, ModelPrice::new(10.0, 30.0)),
+        Self::model_spec("gpt-4-vision-preview", false, ModelPrice::new(10.0, 30.0)),
+        Self::model_spec("gpt-4", false, ModelPrice::new(30.0, 60.0)),
+        Self::model_spec("gpt-3.5-turbo-1106", true, ModelPrice::new(1.0, 2.0)),
+        Self::model_spec("gpt-3.5-turbo", false, ModelPrice::new(0.5, 1.5)),
+    ];
+
+    const fn model_spec(name: &'static str, supports_seed: bool, price: ModelPrice) -> ModelSpec {
+        ModelSpec {
+            name: Cow::Borrowed(name),
+            max_output_tokens: Self::MAX_TOKENS,
+            max_temperature: 2.0,
+            max_stop_sequences: 4,
+            // JSON mode shipped along with `seed` in the 1106 models
+            supports_json_mode: supports_seed,
+            supports_seed,
+            supports_top_k: false,
+            price,
+        }
+    }
 
     pub fn new(secrets: &Secrets) -> Self {
+        Self {
+            client: ProviderClient::new(
+                Self::NAME,
+                secrets,
+                Self::headers(Some(&secrets.open_ai_api_key)),
+            ),
+            name: Self::NAME.to_string(),
+            url: format!("{}{}", Self::BASE_URL, Self::CHAT_PATH),
+            models: Self::MODELS.to_vec(),
+            default_model: OpenAiModel::Gpt4Turbo.name().to_string(),
+            vision_model: Some(OpenAiModel::Gpt4Visual.name().to_string()),
+        }
+    }
+
+    // Any backend implementing the OpenAI chat completions API under its own base URL
+    pub fn compatible(secrets: &Secrets, compatible: &OpenAiCompatibleSecrets) -> Self {
+        let models = compatible
+            .models
+            .iter()
+            .map(|model| ModelSpec {
+                name: Cow::Owned(model.clone()),
+                max_output_tokens: compatible.max_tokens.unwrap_or(Self::MAX_TOKENS),
+                max_temperature: 2.0,
+                max_stop_sequences: 4,
+                supports_seed: true,
+                supports_top_k: false,
+                supports_json_mode: false,
+                price: ModelPrice::new(
+                    compatible.prompt_price.unwrap_or_default(),
+                    compatible.completion_price.unwrap_or_default(),
+                ),
+            })
+            .collect();
+        // Secrets make sure the list is not empty
+        let default_model = compatible.models.first().cloned().unwrap_or_default();
+        Self {
+            client: ProviderClient::new(
+                &compatible.name,
+                secrets,
+                Self::headers(compatible.api_key.as_deref()),
+            ),
+            name: compatible.name.clone(),
+            url: format!(
+                "{}{}",
+                compatible.base_url.trim_end_matches('/'),
+                Self::CHAT_PATH
+            ),
+            models,
+            default_model,
+            vision_model: compatible.vision_model.clone(),
+        }
+    }
+
+    fn headers(api_key: Option<&str>) -> reqwest::header::HeaderMap {
         let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            reqwest::header::AUTHORIZATION,
-            reqwest::header::HeaderValue::from_str(&format!("Bearer {}", secrets.open_ai_api_key))
-                .unwrap(),
-        );
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()
-            .expect("Failed to build reqwest client");
-        Self { client }
+        if let Some(api_key) = api_key {
+            headers.insert(
+                reqwest::header::AUTHORIZATION,
+                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", api_key)).unwrap(),
+            );
+        }
+        headers
+    }
+
+    pub fn circuit(&self) -> Arc<CircuitBreaker> {
+        self.client.circuit()
     }
 
-    pub async fn completion(&self, model: OpenAiModel, prompt: Prompt, message: &str) -> Result<String, CustomError> {
+    pub async fn completion(&self, model: OpenAiModel, prompt: Prompt, message: &str) -> Result<ChatResponse, CustomError> {
         let messages = vec![
             Role::System.new(MessageContent::SimpleText(prompt.prompt())),
             Role::User.new(MessageContent::SimpleText(message.to_string())),
         ];
 
         let payload = Payload {
-            model: model.name(),
+            model: model.name().to_string(),
             messages,
-            temperature: 1,
+            temperature: Some(1.0),
+            top_p: None,
             max_tokens: Some(Self::MAX_TOKENS),
+            stop: None,
+            seed: None,
+            stream: None,
+            stream_options: None,
+            tools: None,
+            tool_choice: None,
+            response_format: None,
         };
 
-        let response: reqwest::Response = self
-            .client
-            .post(Self::API_URL)
-            .json(&payload)
-            .send()
-            .await?;
+        let chat_completion = self.chat_completion(&payload).await?;
+        self.chat_response(chat_completion)
+    }
 
-        if response.status().is_success() {
-            let chat_completion: ChatCompletion = response.json().await?;
-            chat_completion.assistant_response_text()
-        } else {
-            let code = response.status().as_u16();
-            if let Ok(text) = response.text().await {
-                log::error!("Error response: {}", text);
-            }
-            Err(CustomError::NonSuccessfulResponse(code))
-        }
+    pub async fn visual(&self, model: OpenAiModel, prompt: Prompt, base64_image: &str) -> Result<ChatResponse, CustomError> {
+        self.visual_model(model.name(), prompt, base64_image).await
     }
 
-    pub async fn visual(&self, model: OpenAiModel, prompt: Prompt, base64_image: &str) -> Result<String, CustomError> {
+    async fn visual_model(&self, model: &str, prompt: Prompt, base64_image: &str) -> Result<ChatResponse, CustomError> {
         let payload = Payload {
-            model: model.name(),
+            model: model.to_string(),
             messages: vec![Role::User.new(MessageContent::DetailedContent(vec![
                 ContentType::Text {
                     text: prompt.prompt(),
@@ -70,20 +181,213 @@ impl OpenAIApi {
                     image_url: ImageUrl::base64(base64_image),
                 },
             ]))],
-            temperature: 1,
+            temperature: Some(1.0),
+            top_p: None,
             max_tokens: Some(Self::MAX_TOKENS),
+            stop: None,
+            seed: None,
+            stream: None,
+            stream_options: None,
+            tools: None,
+            tool_choice: None,
+            response_format: None,
         };
 
-        let response = self
-            .client
-            .post(Self::API_URL)
-            .json(&payload)
-            .send()
+        let chat_completion = self.chat_completion(&payload).await?;
+        self.chat_response(chat_completion)
+    }
+
+    pub async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, CustomError> {
+        let payload = self.chat_payload(request, None);
+        let chat_completion = self.chat_completion(&payload).await?;
+        self.chat_response(chat_completion)
+    }
+
+    fn chat_response(&self, chat_completion: ChatCompletion) -> Result<ChatResponse, CustomError> {
+        Ok(ChatResponse {
+            provider: self.name.clone(),
+            content: chat_completion.assistant_response_text()?,
+            safety_ratings: Vec::new(),
+            tool_calls: chat_completion.tool_calls(),
+            finish_reason: chat_completion.finish_reason(),
+            usage: chat_completion.usage.as_ref().map(Usage::token_usage),
+            model: chat_completion.model,
+            cost_usd: None,
+            cached: false,
+        })
+    }
+
+    pub async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream, CustomError> {
+        let payload = self.chat_payload(request, Some(true));
+        let response = self.send(&payload).await?;
+        Ok(SseUtils::data_events(response)
+            .try_take_while(|data| future::ready(Ok(data != SseUtils::DONE)))
+            .and_then(|data| async move {
+                let chunk: ChatCompletionChunk = serde_json::from_str(&data)?;
+                Ok(stream::iter(chunk.deltas().into_iter().map(Ok)))
+            })
+            .try_flatten()
+            .boxed())
+    }
+
+    fn chat_payload(&self, request: &ChatRequest, stream: Option<bool>) -> Payload {
+        // Schemas are checked by the gateway, the API is only asked for a JSON object
+        let json_mode = request.response_format.as_ref().is_some_and(|format| format.is_json())
+            && self
+                .models
+                .iter()
+                .any(|model| model.name == request.model && model.supports_json_mode);
+        let messages = request
+            .messages
+            .iter()
+            .map(|message| Message {
+                role: Role::from_chat_role(message.role).role(),
+                // Assistant turns with tool calls and no text are sent with a null content
+                content: (!message.content.is_empty() || message.tool_calls.is_empty())
+                    .then(|| MessageContent::SimpleText(message.content.clone())),
+                tool_calls: (!message.tool_calls.is_empty()).then(|| message.tool_calls.clone()),
+                tool_call_id: message.tool_call_id.clone(),
+            })
+            .collect();
+
+        Payload {
+            model: request.model.clone(),
+            messages,
+            temperature: request.params.temperature,
+            top_p: request.params.top_p,
+            max_tokens: Some(request.params.max_tokens.unwrap_or(Self::MAX_TOKENS)),
+            stop: request.params.stop.clone(),
+            seed: request.params.seed,
+            stream,
+            stream_options: stream.map(|_| StreamOptions { include_usage: true }),
+            tools: (!request.tools.is_empty()).then(|| request.tools.clone()),
+            tool_choice: request.tool_choice.clone(),
+            response_format: json_mode.then(|| json!({ "type": "json_object" })),
+        }
+    }
+
+    // Whisper is only served by OpenAI itself, not by the compatible providers
+    pub async fn transcription(&self, audio_path: &str) -> Result<Transcription, CustomError> {
+        let file_name = std::path::Path::new(audio_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let audio = tokio::fs::read(audio_path).await.map_err(CustomError::IoError)?;
+        let form = reqwest::multipart::Form::new()
+            .text("model", OpenAiModel::Whisper.name())
+            .text("response_format", "verbose_json")
+            .text("timestamp_granularities[]", "segment")
+            .part("file", reqwest::multipart::Part::bytes(audio).file_name(file_name));
+        let url = format!("{}{}", Self::BASE_URL, Self::TRANSCRIPTION_PATH);
+        let transcription: TranscriptionResponse = self
+            .send_request(self.client.post(url).multipart(form))
+            .await?
+            .json()
+            .await?;
+        Ok(Transcription {
+            provider: self.name.clone(),
+            text: transcription.text,
+            language: transcription.language,
+            segments: transcription
+                .segments
+                .into_iter()
+                .map(|segment| TranscriptionSegment {
+                    start: segment.start,
+                    end: segment.end,
+                    text: segment.text.trim().to_string(),
+                })
+                .collect(),
+        })
+    }
+
+    pub async fn speech(&self, request: &SpeechRequest) -> Result<AudioStream, CustomError> {
+        let payload = SpeechPayload {
+            model: OpenAiModel::Tts.name().to_string(),
+            input: request.input.clone(),
+            voice: request.voice,
+            response_format: request.format,
+            speed: request.speed,
+        };
+        let url = format!("{}{}", Self::BASE_URL, Self::SPEECH_PATH);
+        let response = self.send_request(self.client.post(url).json(&payload)).await?;
+        Ok(response.bytes_stream().map_err(CustomError::from).boxed())
+    }
+
+    // USD per image, standard quality
+    pub fn image_price(model: &str, size: &str) -> f64 {
+        match (model, size) {
+            ("dall-e-3", "1024x1024") => 0.04,
+            ("dall-e-3", _) => 0.08,
+            ("dall-e-2", "256x256") => 0.016,
+            ("dall-e-2", "512x512") => 0.018,
+            _ => 0.02,
+        }
+    }
+
+    // DALL·E 3 draws a single image per call
+    pub async fn image_generation(
+        &self,
+        request: &ImageGenerationRequest,
+    ) -> Result<(String, Vec<GeneratedImageData>), CustomError> {
+        let model = request.model.as_deref().unwrap_or(OpenAiModel::DallE3.name());
+        let (sizes, max_n): (&[&str], u32) = match model {
+            "dall-e-3" => (&["1024x1024", "1792x1024", "1024x1792"], 1),
+            "dall-e-2" => (&["256x256", "512x512", "1024x1024"], 10),
+            _ => return Err(CustomError::UnknownModel(model.to_string())),
+        };
+        request.validate(max_n)?;
+        if !sizes.contains(&request.size()) {
+            return Err(CustomError::InvalidRequest(format!(
+                "{} sizes are {}",
+                model,
+                sizes.join(", ")
+            )));
+        }
+        let payload = json!({
+            "model": model,
+            "prompt": request.prompt,
+            "n": request.n,
+            "size": request.size(),
+            "response_format": "b64_json",
+        });
+        let url = format!("{}{}", Self::BASE_URL, Self::IMAGES_PATH);
+        let images: ImagesResponse = self
+            .send_request(self.client.post(url).json(&payload))
+            .await?
+            .json()
             .await?;
+        let images = images
+            .data
+            .into_iter()
+            .map(|image| {
+                Ok(GeneratedImageData {
+                    bytes: general_purpose::STANDARD
+                        .decode(image.b64_json)
+                        .map_err(|e| CustomError::File(format!("Invalid image: {}", e)))?,
+                    revised_prompt: image.revised_prompt,
+                })
+            })
+            .collect::<Result<Vec<GeneratedImageData>, CustomError>>()?;
+        Ok((model.to_string(), images))
+    }
+
+    async fn chat_completion(&self, payload: &Payload) -> Result<ChatCompletion, CustomError> {
+        Ok(self.send(payload).await?.json().await?)
+    }
+
+    async fn send(&self, payload: &Payload) -> Result<reqwest::Response, CustomError> {
+        self.send_request(self.client.post(&self.url).json(payload)).await
+    }
+
+    async fn send_request(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, CustomError> {
+        let response: reqwest::Response = self.client.send(request).await?;
 
         if response.status().is_success() {
-            let chat_completion: ChatCompletion = response.json().await?;
-            chat_completion.assistant_response_text()
+            Ok(response)
         } else {
             let code = response.status().as_u16();
             if let Ok(text) = response.text().await {
@@ -94,41 +398,143 @@ impl OpenAIApi {
     }
 }
 
+#[async_trait]
+impl LlmProvider for OpenAIApi {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            completion: true,
+            vision: self.vision_model.is_some(),
+            embedding: false,
+            streaming: true,
+            tools: true,
+            translation: false,
+        }
+    }
+
+    fn models(&self) -> Vec<ModelSpec> {
+        self.models.clone()
+    }
+
+    fn default_model(&self) -> &str {
+        &self.default_model
+    }
+
+    fn vision_model(&self) -> Option<&str> {
+        self.vision_model.as_deref()
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, CustomError> {
+        self.chat(request).await
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream, CustomError> {
+        self.chat_stream(request).await
+    }
+
+    async fn visual(&self, prompt: Prompt, base64_image: &str) -> Result<ChatResponse, CustomError> {
+        match &self.vision_model {
+            Some(model) => self.visual_model(model, prompt, base64_image).await,
+            None => Err(CustomError::UnsupportedCapability(format!(
+                "{} does not support vision",
+                self.name
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ChatCompletion {
     id: String,
     object: String,
     created: i64,
     model: String,
-    usage: Usage,
+    usage: Option<Usage>,
     choices: Vec<Choice>,
 }
 
 impl ChatCompletion {
+    fn assistant_message(&self) -> Result<&Message, CustomError> {
+        self.choices
+            .iter()
+            .map(|choice| &choice.message)
+            .find(|message| message.role == Role::Assistant.role())
+            .ok_or(CustomError::NoContentFromAssistant)
+    }
+
     fn assistant_response_text(&self) -> Result<String, CustomError> {
-        if let Some(assitant_choise) = self.choices.iter().find(|choice| {
-            if choice.message.role == Role::Assistant.role() {
-                true
-            } else {
-                false
-            }
-        }) {
-            let content = &assitant_choise.message.content;
-            match content {
-                MessageContent::SimpleText(text) => Ok(text.to_owned()),
-                _ => Err(CustomError::NoContentFromAssistant),
-            }
-        } else {
-            Err(CustomError::NoContentFromAssistant)
+        let message = self.assistant_message()?;
+        match &message.content {
+            Some(MessageContent::SimpleText(text)) => Ok(text.to_owned()),
+            // Turns calling tools may come without text
+            None if message.tool_calls.is_some() => Ok(String::new()),
+            _ => Err(CustomError::NoContentFromAssistant),
         }
     }
+
+    fn tool_calls(&self) -> Vec<ToolCall> {
+        self.assistant_message()
+            .ok()
+            .and_then(|message| message.tool_calls.clone())
+            .unwrap_or_default()
+    }
+
+    fn finish_reason(&self) -> Option<String> {
+        self.choices
+            .iter()
+            .find_map(|choice| choice.finish_reason.clone())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionChunk {
+    choices: Vec<ChunkChoice>,
+    usage: Option<Usage>,
+}
+
+impl ChatCompletionChunk {
+    fn deltas(&self) -> Vec<ChatDelta> {
+        let text: String = self
+            .choices
+            .iter()
+            .filter_map(|choice| choice.delta.content.clone())
+            .collect();
+        (!text.is_empty())
+            .then_some(ChatDelta::Text(text))
+            .into_iter()
+            .chain(self.usage.as_ref().map(|usage| ChatDelta::Usage(usage.token_usage())))
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    delta: Delta,
+}
+
+#[derive(Debug, Deserialize)]
+struct Delta {
+    content: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct Usage {
-    prompt_tokens: i64,
-    completion_tokens: i64,
-    total_tokens: i64,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    total_tokens: u32,
+}
+
+impl Usage {
+    fn token_usage(&self) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens: self.prompt_tokens,
+            completion_tokens: self.completion_tokens,
+            total_tokens: self.total_tokens,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -173,7 +579,11 @@ enum MessageContent {
 #[derive(Debug, Serialize, Deserialize)]
 struct Message {
     role: String,
-    content: MessageContent,
+    content: Option<MessageContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<ToolCall>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
 }
 
 // Struct for the payload
@@ -181,8 +591,31 @@ struct Message {
 struct Payload {
     model: String,
     messages: Vec<Message>,
-    temperature: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<JsonValue>,
+}
+
+// Asks for a last chunk with the usage of the whole stream
+#[derive(Debug, Serialize, Deserialize)]
+struct StreamOptions {
+    include_usage: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -190,21 +623,34 @@ enum Role {
     System,
     User,
     Assistant,
+    Tool,
 }
 
 impl Role {
+    fn from_chat_role(role: ChatRole) -> Self {
+        match role {
+            ChatRole::System => Role::System,
+            ChatRole::User => Role::User,
+            ChatRole::Assistant => Role::Assistant,
+            ChatRole::Tool => Role::Tool,
+        }
+    }
+
     fn role(&self) -> String {
         match self {
             Role::System => "system".to_string(),
             Role::User => "user".to_string(),
             Role::Assistant => "assistant".to_string(),
+            Role::Tool => "tool".to_string(),
         }
     }
 
     fn new(&self, content: MessageContent) -> Message {
         Message {
             role: self.role(),
-            content: content,
+            content: Some(content),
+            tool_calls: None,
+            tool_call_id: None,
         }
     }
 }
@@ -212,13 +658,93 @@ impl Role {
 pub enum OpenAiModel {
     Gpt4Turbo,
     Gpt4Visual,
+    Whisper,
+    Tts,
+    DallE3,
 }
 
 impl OpenAiModel {
-    fn name(&self) -> String {
+    pub fn name(&self) -> &'static str {
         match self {
-            OpenAiModel::Gpt4Turbo => "gpt-4-1106-preview".to_string(),
-            OpenAiModel::Gpt4Visual => "gpt-4-vision-preview".to_string(),
+            OpenAiModel::Gpt4Turbo => "gpt-4-1106-preview",
+            OpenAiModel::Gpt4Visual => "gpt-4-vision-preview",
+            OpenAiModel::Whisper => "whisper-1",
+            OpenAiModel::Tts => "tts-1",
+            OpenAiModel::DallE3 => "dall-e-3",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct TranscriptionResponse {
+    text: String,
+    language: Option<String>,
+    #[serde(default)]
+    segments: Vec<TranscriptionResponseSegment>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TranscriptionResponseSegment {
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+#[derive(Serialize, Debug)]
+struct SpeechPayload {
+    model: String,
+    input: String,
+    voice: SpeechVoice,
+    response_format: SpeechFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speed: Option<f32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ImagesResponse {
+    data: Vec<ImageData>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ImageData {
+    b64_json: String,
+    revised_prompt: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{chat_message::ChatMessage, response_format::ResponseFormat};
+
+    fn request(model: &str, response_format: Option<ResponseFormat>) -> ChatRequest {
+        ChatRequest {
+            model: model.to_string(),
+            messages: vec![ChatMessage::new(ChatRole::User, "List three colors".to_string())],
+            params: Default::default(),
+            tools: Vec::new(),
+            tool_choice: None,
+            response_format,
+            safety_settings: Vec::new(),
         }
     }
+
+    #[test]
+    fn json_mode_is_requested_from_models_supporting_it() {
+        let api = OpenAIApi::new(&Secrets::default());
+        let payload = api.chat_payload(
+            &request("gpt-4-1106-preview", Some(ResponseFormat::JsonObject)),
+            None,
+        );
+        assert_eq!(payload.response_format, Some(json!({ "type": "json_object" })));
+    }
+
+    #[test]
+    fn json_mode_is_left_to_instructions_for_other_models() {
+        let api = OpenAIApi::new(&Secrets::default());
+        let payload = api.chat_payload(&request("gpt-4", Some(ResponseFormat::JsonObject)), None);
+        assert_eq!(payload.response_format, None);
+
+        let payload = api.chat_payload(&request("gpt-4-1106-preview", Some(ResponseFormat::Text)), None);
+        assert_eq!(payload.response_format, None);
+    }
 }
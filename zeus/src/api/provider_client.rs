@@ -0,0 +1,67 @@
+use std::{sync::Arc, time::Duration};
+
+use reqwest::{header::HeaderMap, IntoUrl, RequestBuilder, Response};
+
+use crate::{
+    api::{circuit_breaker::CircuitBreaker, retry_policy::RetryPolicy},
+    models::custom_error::CustomError,
+    repository::secrets::Secrets,
+};
+
+// Outbound HTTP of a single provider: timeouts, retries and the circuit breaker
+pub struct ProviderClient {
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    circuit: Arc<CircuitBreaker>,
+    read_timeout: Duration,
+}
+
+impl ProviderClient {
+    const CONNECT_TIMEOUT_MS: u64 = 5_000;
+    const READ_TIMEOUT_MS: u64 = 60_000;
+    const TOTAL_TIMEOUT_MS: u64 = 300_000;
+
+    pub fn new(name: &str, secrets: &Secrets, headers: HeaderMap) -> Self {
+        let timeouts = secrets.timeouts(name);
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .connect_timeout(Duration::from_millis(
+                timeouts.connect_ms.unwrap_or(Self::CONNECT_TIMEOUT_MS),
+            ))
+            // Covers streamed bodies as well
+            .timeout(Duration::from_millis(
+                timeouts.total_ms.unwrap_or(Self::TOTAL_TIMEOUT_MS),
+            ))
+            .build()
+            .expect("Failed to build reqwest client");
+        Self {
+            client,
+            retry: RetryPolicy::new(secrets),
+            circuit: Arc::new(CircuitBreaker::new(name, secrets)),
+            read_timeout: Duration::from_millis(timeouts.read_ms.unwrap_or(Self::READ_TIMEOUT_MS)),
+        }
+    }
+
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    // Server errors, timeouts and connection failures count against the circuit, rate limits don't
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, CustomError> {
+        self.circuit.acquire()?;
+        let result = self.retry.send(request, self.read_timeout).await;
+        self.circuit.record(match &result {
+            Ok(response) => !response.status().is_server_error(),
+            Err(e) => !e.is_transient(),
+        });
+        result
+    }
+
+    pub fn circuit(&self) -> Arc<CircuitBreaker> {
+        Arc::clone(&self.circuit)
+    }
+}
@@ -0,0 +1,187 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde::Serialize;
+
+use crate::{
+    api::{
+        llm_provider::{ChatRequest, ChatResponse, LlmProvider, ProviderCapabilities, RoutedStream},
+        model_catalog::ModelSpec,
+    },
+    models::custom_error::CustomError,
+};
+
+// A provider along with the request it is sent, the model may differ after a fallback
+type Route = (Arc<dyn LlmProvider>, ChatRequest);
+
+#[derive(Default)]
+pub struct ProviderRegistry {
+    providers: HashMap<String, Arc<dyn LlmProvider>>,
+    fallbacks: HashMap<String, Vec<String>>,
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Names and models must be unique so that routing by either is unambiguous
+    pub fn register(&mut self, provider: Arc<dyn LlmProvider>) -> Result<(), CustomError> {
+        if self.providers.contains_key(provider.name()) {
+            return Err(CustomError::Configuration(format!(
+                "provider {} is registered twice",
+                provider.name()
+            )));
+        }
+        for model in provider.models() {
+            if let Ok(existing) = self.provider_for_model(&model.name) {
+                return Err(CustomError::Configuration(format!(
+                    "model {} is served by both {} and {}",
+                    model.name,
+                    existing.name(),
+                    provider.name()
+                )));
+            }
+        }
+        self.providers.insert(provider.name().to_string(), provider);
+        Ok(())
+    }
+
+    // Models to try in order when the primary model fails with a transient error
+    pub fn set_fallbacks(&mut self, fallbacks: HashMap<String, Vec<String>>) {
+        self.fallbacks = fallbacks;
+    }
+
+    pub fn provider(&self, name: &str) -> Result<Arc<dyn LlmProvider>, CustomError> {
+        self.providers
+            .get(name)
+            .cloned()
+            .ok_or_else(|| CustomError::UnknownProvider(name.to_string()))
+    }
+
+    pub fn provider_for_model(&self, model: &str) -> Result<Arc<dyn LlmProvider>, CustomError> {
+        self.providers
+            .values()
+            .find(|provider| provider.model(model).is_ok())
+            .cloned()
+            .ok_or_else(|| CustomError::UnknownModel(model.to_string()))
+    }
+
+    pub fn providers(&self) -> Vec<ProviderInfo> {
+        let mut providers: Vec<ProviderInfo> = self
+            .providers
+            .values()
+            .map(|provider| ProviderInfo {
+                name: provider.name().to_string(),
+                capabilities: provider.capabilities(),
+                models: provider.models(),
+            })
+            .collect();
+        providers.sort_by(|a, b| a.name.cmp(&b.name));
+        providers
+    }
+
+    pub async fn chat(
+        &self,
+        provider: Arc<dyn LlmProvider>,
+        request: &ChatRequest,
+    ) -> Result<ChatResponse, CustomError> {
+        let mut routes = self.routes(provider, request)?.into_iter().peekable();
+        while let Some((provider, request)) = routes.next() {
+            match provider.chat(&request).await {
+                Ok(mut response) => {
+                    log::info!("{} answered by {}", request.model, provider.name());
+                    response.cost_usd = provider.cost(&request.model, response.usage);
+                    return Ok(response);
+                }
+                Err(e) if e.is_transient() && routes.peek().is_some() => {
+                    log::warn!("{} failed on {}: {}", request.model, provider.name(), e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(CustomError::UnknownModel(request.model.clone()))
+    }
+
+    // Falls back only while opening the stream, failures after the first delta end the stream
+    pub async fn chat_stream(
+        &self,
+        provider: Arc<dyn LlmProvider>,
+        request: &ChatRequest,
+    ) -> Result<RoutedStream, CustomError> {
+        if !request.tools.is_empty() {
+            return Err(CustomError::UnsupportedCapability(
+                "tool calls are not streamed, send the request without `stream`".to_string(),
+            ));
+        }
+        if request.response_format.as_ref().is_some_and(|format| format.is_json()) {
+            return Err(CustomError::UnsupportedCapability(
+                "JSON answers are validated whole and cannot be streamed".to_string(),
+            ));
+        }
+        let mut routes = self.routes(provider, request)?.into_iter().peekable();
+        while let Some((provider, request)) = routes.next() {
+            match provider.chat_stream(&request).await {
+                Ok(stream) => {
+                    log::info!("{} streamed by {}", request.model, provider.name());
+                    return Ok(RoutedStream {
+                        provider: provider.name().to_string(),
+                        price: provider.model(&request.model).ok().map(|spec| spec.price),
+                        model: request.model,
+                        stream,
+                    });
+                }
+                Err(e) if e.is_transient() && routes.peek().is_some() => {
+                    log::warn!("{} failed on {}: {}", request.model, provider.name(), e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(CustomError::UnknownModel(request.model.clone()))
+    }
+
+    // The requested model first, then its configured fallbacks that can serve the same parameters
+    fn routes(
+        &self,
+        provider: Arc<dyn LlmProvider>,
+        request: &ChatRequest,
+    ) -> Result<Vec<Route>, CustomError> {
+        Self::supports(provider.as_ref(), request)?;
+        let mut routes = vec![(provider, request.clone())];
+        for model in self.fallbacks.get(&request.model).into_iter().flatten() {
+            let fallback = self.provider_for_model(model).and_then(|provider| {
+                provider.model(model)?.validate(&request.params)?;
+                Self::supports(provider.as_ref(), request)?;
+                Ok(provider)
+            });
+            match fallback {
+                Ok(provider) => routes.push((
+                    provider,
+                    ChatRequest {
+                        model: model.clone(),
+                        ..request.clone()
+                    },
+                )),
+                Err(e) => log::warn!("Skipping fallback {} for {}: {}", model, request.model, e),
+            }
+        }
+        Ok(routes)
+    }
+
+    // Tool turns would otherwise be degraded to plain text by providers without tool calling
+    fn supports(provider: &dyn LlmProvider, request: &ChatRequest) -> Result<(), CustomError> {
+        if request.uses_tools() && !provider.capabilities().tools {
+            return Err(CustomError::InvalidRequest(format!(
+                "{} does not support tool calling",
+                provider.name()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ProviderInfo {
+    pub name: String,
+    pub capabilities: ProviderCapabilities,
+    pub models: Vec<ModelSpec>,
+}
@@ -0,0 +1,256 @@
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    RequestBuilder, Response, StatusCode,
+};
+
+use crate::{models::custom_error::CustomError, repository::secrets::Secrets};
+
+// Shared by every outbound provider call, retries transient failures with jittered exponential backoff
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(secrets: &Secrets) -> Self {
+        Self {
+            max_attempts: secrets.retry_max_attempts.max(1),
+            base_delay: Duration::from_millis(secrets.retry_base_delay_ms),
+            max_delay: Duration::from_millis(secrets.retry_max_delay_ms),
+        }
+    }
+
+    // The last response is returned as is, callers keep mapping non successful statuses to errors.
+    // Each attempt waits at most `read_timeout` for the response headers.
+    pub async fn send(
+        &self,
+        request: RequestBuilder,
+        read_timeout: Duration,
+    ) -> Result<Response, CustomError> {
+        let mut attempt = 1;
+        loop {
+            // Requests with a streaming body can't be replayed
+            let next = match request.try_clone() {
+                Some(next) if attempt < self.max_attempts => next,
+                _ => return Self::attempt(request, read_timeout).await,
+            };
+            let delay = match Self::attempt(next, read_timeout).await {
+                Ok(response) if Self::is_retryable(response.status()) => {
+                    match self.delay(attempt, Self::retry_after(response.headers())) {
+                        Some(delay) => {
+                            log::warn!(
+                                "Retrying {} after {}, attempt {}",
+                                response.url(),
+                                response.status(),
+                                attempt
+                            );
+                            delay
+                        }
+                        None => return Ok(response),
+                    }
+                }
+                Err(e) if e.is_transient() => {
+                    log::warn!("Retrying after {}, attempt {}", e, attempt);
+                    self.backoff(attempt)
+                }
+                result => return result,
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn attempt(request: RequestBuilder, read_timeout: Duration) -> Result<Response, CustomError> {
+        match tokio::time::timeout(read_timeout, request.send()).await {
+            Ok(response) => Ok(response?),
+            Err(_) => Err(CustomError::Timeout(format!(
+                "no response within {}ms",
+                read_timeout.as_millis()
+            ))),
+        }
+    }
+
+    fn is_retryable(status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::REQUEST_TIMEOUT
+                | StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        )
+    }
+
+    // A Retry-After beyond the max delay gives up, so a fallback provider can answer instead
+    fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        match retry_after {
+            Some(retry_after) if retry_after > self.max_delay => None,
+            Some(retry_after) => Some(retry_after),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+
+    // Equal jitter: half of the exponential delay is fixed, the other half random
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        let half = exponential / 2;
+        half + rand::thread_rng().gen_range(Duration::ZERO..=half)
+    }
+
+    // Either delay seconds or an HTTP date
+    fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+        let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+        if let Ok(seconds) = value.trim().parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+        let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+        (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .ok()
+            .or(Some(Duration::ZERO))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1_000),
+        }
+    }
+
+    fn retry_after(value: &str) -> Option<Duration> {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(value).unwrap());
+        RetryPolicy::retry_after(&headers)
+    }
+
+    #[test]
+    fn backoff_doubles_within_the_jitter_bounds() {
+        let policy = policy(5);
+        for (attempt, exponential) in [(1, 100), (2, 200), (3, 400), (4, 800)] {
+            for _ in 0..100 {
+                let delay = policy.backoff(attempt).as_millis();
+                assert!(
+                    (exponential / 2..=exponential).contains(&delay),
+                    "attempt {} waited {}ms",
+                    attempt,
+                    delay
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped_at_the_max_delay() {
+        let policy = policy(5);
+        for attempt in [5, 10, 64] {
+            let delay = policy.backoff(attempt);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1_000));
+        }
+    }
+
+    #[test]
+    fn retry_after_in_seconds() {
+        assert_eq!(retry_after("2"), Some(Duration::from_secs(2)));
+        assert_eq!(retry_after(" 0 "), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_as_http_date() {
+        let date = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        let delay = retry_after(&date).unwrap();
+        assert!(delay > Duration::from_secs(28) && delay <= Duration::from_secs(30));
+
+        assert_eq!(retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_ignores_invalid_values() {
+        assert_eq!(retry_after("soon"), None);
+        assert_eq!(RetryPolicy::retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn retry_after_beyond_the_max_delay_gives_up() {
+        let policy = policy(5);
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(2))), None);
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_millis(300))),
+            Some(Duration::from_millis(300))
+        );
+        assert!(policy.delay(1, None).is_some());
+    }
+
+    #[tokio::test]
+    async fn send_retries_transient_statuses() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503).insert_header("retry-after", "0"))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let response = policy(3)
+            .send(reqwest::Client::new().get(server.uri()), Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn send_returns_the_last_response_after_max_attempts() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503).insert_header("retry-after", "0"))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let response = policy(2)
+            .send(reqwest::Client::new().get(server.uri()), Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn send_does_not_retry_client_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = policy(3)
+            .send(reqwest::Client::new().get(server.uri()), Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
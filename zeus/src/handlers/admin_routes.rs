@@ -0,0 +1,58 @@
+use actix_web::{
+    delete, get, post,
+    web::{self, Data, Json},
+    HttpResponse, Responder,
+};
+use serde_json::json;
+
+use crate::{
+    handlers::response_common,
+    models::{
+        api_key::CreateApiKeyRequest, app_dependency::AppDependency, usage_query::UsageQuery,
+    },
+};
+
+pub fn v1_admin_router(conf: &mut web::ServiceConfig) {
+    conf.service(circuits)
+        .service(usage)
+        .service(cache)
+        .service(create_key)
+        .service(list_keys)
+        .service(revoke_key);
+}
+
+#[get("/circuits")]
+async fn circuits(data: Data<AppDependency>) -> impl Responder {
+    HttpResponse::Ok().json(json!({"status": "success", "message": data.admin_usecase.circuits()}))
+}
+
+// Daily token usage per endpoint and model, optionally bounded by ?from=YYYY-MM-DD&to=YYYY-MM-DD
+#[get("/usage")]
+async fn usage(data: Data<AppDependency>, query: web::Query<UsageQuery>) -> impl Responder {
+    let result = data.admin_usecase.usage(&query).await;
+    response_common::create_response(result)
+}
+
+#[get("/cache")]
+async fn cache(data: Data<AppDependency>) -> impl Responder {
+    HttpResponse::Ok().json(json!({"status": "success", "message": data.admin_usecase.cache()}))
+}
+
+// The plain key is only returned in this response
+#[post("/keys")]
+async fn create_key(data: Data<AppDependency>, body: Json<CreateApiKeyRequest>) -> impl Responder {
+    let result = data.api_key_usecase.create(&body).await;
+    response_common::create_response(result)
+}
+
+#[get("/keys")]
+async fn list_keys(data: Data<AppDependency>) -> impl Responder {
+    let result = data.api_key_usecase.list().await;
+    response_common::create_response(result)
+}
+
+#[delete("/keys/{id}")]
+async fn revoke_key(data: Data<AppDependency>, id: web::Path<i32>) -> impl Responder {
+    let result = data.api_key_usecase.revoke(id.into_inner()).await;
+    response_common::create_response(result)
+}
@@ -0,0 +1,76 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    web::Data,
+    Error, HttpMessage,
+};
+
+use crate::{
+    handlers::response_common,
+    models::{app_dependency::AppDependency, custom_error::CustomError},
+};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+// Gateway keys, accepted as `Authorization: Bearer <key>` like OpenAI clients send them or as `X-Api-Key`
+pub async fn require_api_key<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let Some(data) = req.app_data::<Data<AppDependency>>().cloned() else {
+        return Ok(reject(req, CustomError::Unauthorized("no key store".to_string())));
+    };
+    match data.api_key_usecase.authenticate(presented_key(&req)).await {
+        Ok(identity) => {
+            log::info!(
+                "{} {} by API key {} ({}) of project {}",
+                req.method(),
+                req.path(),
+                identity.id,
+                identity.name,
+                identity.project
+            );
+            req.extensions_mut().insert(identity);
+            Ok(next.call(req).await?.map_into_left_body())
+        }
+        Err(e) => Ok(reject(req, e)),
+    }
+}
+
+// The admin key from ADMIN_API_KEY, gateway keys cannot manage other keys
+pub async fn require_admin_key<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let result = match req.app_data::<Data<AppDependency>>() {
+        Some(data) => data.api_key_usecase.authenticate_admin(presented_key(&req)),
+        None => Err(CustomError::Unauthorized("no key store".to_string())),
+    };
+    match result {
+        Ok(()) => Ok(next.call(req).await?.map_into_left_body()),
+        Err(e) => Ok(reject(req, e)),
+    }
+}
+
+fn presented_key(req: &ServiceRequest) -> Option<&str> {
+    let headers = req.headers();
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| {
+            headers
+                .get(API_KEY_HEADER)
+                .and_then(|value| value.to_str().ok())
+        })
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+fn reject<B>(req: ServiceRequest, e: CustomError) -> ServiceResponse<EitherBody<B>> {
+    log::warn!("Rejected {} {}: {}", req.method(), req.path(), e);
+    req.into_response(response_common::create_openai_response::<()>(Err(e)))
+        .map_into_right_body()
+}
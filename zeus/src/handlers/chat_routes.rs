@@ -0,0 +1,60 @@
+use actix_web::{
+    post,
+    web::{self, Data},
+    HttpRequest, HttpResponse,
+};
+use futures_util::TryStreamExt;
+
+use crate::{
+    handlers::response_common,
+    models::{
+        app_dependency::AppDependency, call_context::CallContext,
+        chat_completion_request::ChatCompletionRequest,
+    },
+};
+
+pub fn v1_chat_router(conf: &mut web::ServiceConfig) {
+    conf.service(chat_completions);
+}
+
+// OpenAI compatible, the model selects the provider
+
+#[post("/completions")]
+async fn chat_completions(
+    data: Data<AppDependency>,
+    http_req: HttpRequest,
+    req: web::Json<ChatCompletionRequest>,
+) -> HttpResponse {
+    let context = CallContext::new(&http_req);
+    if req.stream.unwrap_or(false) {
+        match data.chat_usecase.chat_completion_stream(&context, &req).await {
+            Ok(stream) => response_common::with_route(
+                response_common::create_sse_response(
+                    stream
+                        .chunks
+                        .and_then(|chunk| async move { Ok(serde_json::to_string(&chunk)?) }),
+                ),
+                &stream.provider,
+                &stream.model,
+            ),
+            Err(e) => response_common::create_openai_response::<()>(Err(e)),
+        }
+    } else {
+        match data.chat_usecase.chat_completion(&context, &req).await {
+            Ok(response) => {
+                let (provider, model) = (response.provider.clone(), response.model.clone());
+                let cached = response.cached;
+                response_common::with_cache_status(
+                    response_common::with_route(
+                        response_common::create_openai_response(Ok(response)),
+                        &provider,
+                        &model,
+                    ),
+                    &context,
+                    cached,
+                )
+            }
+            Err(e) => response_common::create_openai_response::<()>(Err(e)),
+        }
+    }
+}
@@ -2,14 +2,16 @@ use actix_multipart::form::{tempfile::TempFile, MultipartForm};
 use actix_web::{
     post,
     web::{self, Data},
-    HttpResponse, Responder, get,
+    HttpRequest, HttpResponse, Responder, get,
 };
+use futures_util::TryStreamExt;
 use serde_json::json;
 
 use crate::{models::{
-    app_dependency::AppDependency, completion_model::CompletionRequest,
-    vision_request::VisionRequest, file_upload_request::UploadForm, embedding_body_request::EmbeddintBodyRequest,
-}, api::google_places::GoogleGeocodeApiRequest, handlers::response_common};
+    app_dependency::AppDependency, call_context::CallContext, completion_model::CompletionRequest,
+    vision_request::VisionRequest, speech_request::SpeechRequest,
+    image_generation::ImageGenerationRequest, translation::TranslationRequest, file_upload_request::UploadForm, embedding_body_request::EmbeddintBodyRequest,
+}, api::{cloudflare_ai::CloudflareApi, google_gemini::GeminiApi, google_places::GoogleGeocodeApiRequest, open_ai::OpenAIApi}, handlers::response_common};
 
 pub fn v1_ext_router(conf: &mut web::ServiceConfig) {
     conf.service(completion_gpt)
@@ -19,7 +21,142 @@ pub fn v1_ext_router(conf: &mut web::ServiceConfig) {
         .service(visuak_gpt)
         .service(visual_gemini)
         .service(embedding)
-        .service(places_geocoding);
+        .service(image_classification)
+        .service(object_detection)
+        .service(places_geocoding)
+        .service(providers)
+        .service(completion)
+        .service(visual)
+        .service(transcription)
+        .service(speech)
+        .service(image_generation)
+        .service(translation);
+}
+
+// LLM providers
+
+#[get("/providers")]
+async fn providers(data: Data<AppDependency>) -> impl Responder {
+    HttpResponse::Ok().json(json!({"status": "success", "message": data.ext_api_usecase.providers()}))
+}
+
+#[post("/completion/{provider}")]
+async fn completion(
+    data: Data<AppDependency>,
+    http_req: HttpRequest,
+    provider: web::Path<String>,
+    req: web::Json<CompletionRequest>,
+) -> impl Responder {
+    completion_response(&data, &CallContext::new(&http_req), &provider, &req).await
+}
+
+async fn completion_response(
+    data: &AppDependency,
+    context: &CallContext,
+    provider: &str,
+    req: &CompletionRequest,
+) -> HttpResponse {
+    if req.stream.unwrap_or(false) {
+        match data.ext_api_usecase.completion_stream(context, provider, req).await {
+            Ok(routed) => response_common::with_route(
+                response_common::create_sse_response(
+                    routed.stream.map_ok(|text| {
+                        json!({"status": "success", "message": text}).to_string()
+                    }),
+                ),
+                &routed.provider,
+                &routed.model,
+            ),
+            Err(e) => response_common::create_response::<String>(Err(e)),
+        }
+    } else {
+        match data.ext_api_usecase.completion(context, provider, req).await {
+            Ok(response) => response_common::with_cache_status(
+                response_common::with_route(
+                    response_common::create_response::<String>(Ok(response.content)),
+                    &response.provider,
+                    &response.model,
+                ),
+                context,
+                response.cached,
+            ),
+            Err(e) => response_common::create_response::<String>(Err(e)),
+        }
+    }
+}
+
+#[post("/translation")]
+async fn translation(
+    data: Data<AppDependency>,
+    http_req: HttpRequest,
+    req: web::Json<TranslationRequest>,
+) -> impl Responder {
+    let result = data
+        .ext_api_usecase
+        .translation(&CallContext::new(&http_req), &req)
+        .await;
+    response_common::create_response(result)
+}
+
+#[post("/visual/{provider}")]
+async fn visual(
+    data: Data<AppDependency>,
+    http_req: HttpRequest,
+    provider: web::Path<String>,
+    MultipartForm(form): MultipartForm<UploadForm>,
+) -> impl Responder {
+    let f: TempFile = form.file;
+    if f.size == 0 {
+        return HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": "File size is 0"}));
+    }
+    let result = data.ext_api_usecase.visual(&CallContext::new(&http_req), &provider, f).await;
+    response_common::create_response(result)
+}
+
+#[post("/transcription/{provider}")]
+async fn transcription(
+    data: Data<AppDependency>,
+    http_req: HttpRequest,
+    provider: web::Path<String>,
+    MultipartForm(form): MultipartForm<UploadForm>,
+) -> impl Responder {
+    let f: TempFile = form.file;
+    if f.size == 0 {
+        return HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": "File size is 0"}));
+    }
+    let result = data
+        .media_usecase
+        .transcription(&CallContext::new(&http_req), &provider, f)
+        .await;
+    response_common::create_response(result)
+}
+
+#[post("/speech")]
+async fn speech(
+    data: Data<AppDependency>,
+    http_req: HttpRequest,
+    req: web::Json<SpeechRequest>,
+) -> impl Responder {
+    match data.media_usecase.speech(&CallContext::new(&http_req), &req).await {
+        Ok(audio) => response_common::create_audio_response(req.format.content_type(), audio),
+        Err(e) => response_common::create_response::<String>(Err(e)),
+    }
+}
+
+#[post("/image_generation/{provider}")]
+async fn image_generation(
+    data: Data<AppDependency>,
+    http_req: HttpRequest,
+    provider: web::Path<String>,
+    req: web::Json<ImageGenerationRequest>,
+) -> impl Responder {
+    let result = data
+        .media_usecase
+        .image_generation(&CallContext::new(&http_req), &provider, &req)
+        .await;
+    response_common::create_response(result)
 }
 
 // Google Vision API
@@ -46,24 +183,25 @@ async fn places_geocoding(
 #[post("/text_gpt")]
 async fn completion_gpt(
     data: Data<AppDependency>,
+    http_req: HttpRequest,
     req: web::Json<CompletionRequest>,
 ) -> impl Responder {
-    let result = data.ext_api_usecase.gpt_completion(&req.query).await;
-    response_common::create_response(result)
+    completion_response(&data, &CallContext::new(&http_req), OpenAIApi::NAME, &req).await
 }
 
 #[post("/visual_gpt")]
 async fn visuak_gpt(
     data: Data<AppDependency>,
+    http_req: HttpRequest,
     MultipartForm(form): MultipartForm<UploadForm>,
 ) -> impl Responder {
     let f: TempFile = form.file;
-    if f.size <= 0 {
+    if f.size == 0 {
         return HttpResponse::InternalServerError()
             .json(json!({"status": "error","message": "File size is 0"}));
     }
 
-    let result =  data.ext_api_usecase.gpt_visual(f).await;
+    let result =  data.ext_api_usecase.visual(&CallContext::new(&http_req), OpenAIApi::NAME, f).await;
     response_common::create_response(result)
 }
 
@@ -72,18 +210,58 @@ async fn visuak_gpt(
 #[post("/text_llama")]
 async fn completion_llama(
     data: Data<AppDependency>,
+    http_req: HttpRequest,
     req: web::Json<CompletionRequest>,
 ) -> impl Responder {
-    let result = data.ext_api_usecase.llama_completion(&req.query).await;
-    response_common::create_response(result)
+    completion_response(&data, &CallContext::new(&http_req), CloudflareApi::NAME, &req).await
 }
 
 #[post("/embedding")]
 async fn embedding(
     data: Data<AppDependency>,
+    http_req: HttpRequest,
     req: web::Json<EmbeddintBodyRequest>,
 ) -> impl Responder {
-    let result = data.ext_api_usecase.embedding(&req).await;
+    let result = data
+        .ext_api_usecase
+        .embedding(&CallContext::new(&http_req), CloudflareApi::NAME, &req)
+        .await;
+    response_common::create_response(result)
+}
+
+#[post("/image_classification")]
+async fn image_classification(
+    data: Data<AppDependency>,
+    http_req: HttpRequest,
+    MultipartForm(form): MultipartForm<UploadForm>,
+) -> impl Responder {
+    let f: TempFile = form.file;
+    if f.size == 0 {
+        return HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": "File size is 0"}));
+    }
+    let result = data
+        .media_usecase
+        .classification(&CallContext::new(&http_req), f)
+        .await;
+    response_common::create_response(result)
+}
+
+#[post("/object_detection")]
+async fn object_detection(
+    data: Data<AppDependency>,
+    http_req: HttpRequest,
+    MultipartForm(form): MultipartForm<UploadForm>,
+) -> impl Responder {
+    let f: TempFile = form.file;
+    if f.size == 0 {
+        return HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": "File size is 0"}));
+    }
+    let result = data
+        .media_usecase
+        .detection(&CallContext::new(&http_req), f)
+        .await;
     response_common::create_response(result)
 }
 
@@ -92,22 +270,23 @@ async fn embedding(
 #[post("/text_gemini")]
 async fn completion_gemini(
     data: Data<AppDependency>,
+    http_req: HttpRequest,
     req: web::Json<CompletionRequest>,
 ) -> impl Responder {
-    let result = data.ext_api_usecase.gemini_completion(&req.query).await;
-    response_common::create_response(result)
+    completion_response(&data, &CallContext::new(&http_req), GeminiApi::NAME, &req).await
 }
 
 #[post("/visual_gemini")]
 async fn visual_gemini(
     data: Data<AppDependency>,
+    http_req: HttpRequest,
     MultipartForm(form): MultipartForm<UploadForm>,
 ) -> impl Responder {
     let f: TempFile = form.file;
-    if f.size <= 0 {
+    if f.size == 0 {
         return HttpResponse::InternalServerError()
             .json(json!({"status": "error","message": "File size is 0"}));
     }
-    let result = data.ext_api_usecase.gemini_visual(f).await;
+    let result = data.ext_api_usecase.visual(&CallContext::new(&http_req), GeminiApi::NAME, f).await;
     response_common::create_response(result)
 }
\ No newline at end of file
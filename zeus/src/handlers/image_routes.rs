@@ -0,0 +1,16 @@
+use actix_web::{get, web::{self, Data}, HttpResponse, Responder};
+
+use crate::{models::app_dependency::AppDependency, handlers::response_common};
+
+// Served without an API key so the returned URLs open in browsers, the file names are random
+pub fn v1_images_router(conf: &mut web::ServiceConfig) {
+    conf.service(generated_image);
+}
+
+#[get("/{file_name}")]
+async fn generated_image(data: Data<AppDependency>, file_name: web::Path<String>) -> impl Responder {
+    match data.media_usecase.generated_image(&file_name) {
+        Ok(image) => HttpResponse::Ok().content_type("image/png").body(image),
+        Err(e) => response_common::create_response::<String>(Err(e)),
+    }
+}
@@ -1,4 +1,9 @@
 pub mod routes;
 mod ext_routes;
 mod poi_routes;
-mod response_common;
\ No newline at end of file
+mod response_common;
+mod chat_routes;
+mod admin_routes;
+pub mod auth_middleware;
+mod rate_limit_middleware;
+mod image_routes;
\ No newline at end of file
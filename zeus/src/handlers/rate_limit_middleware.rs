@@ -0,0 +1,84 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER},
+    middleware::Next,
+    web::Data,
+    Error, HttpMessage,
+};
+
+use crate::{
+    handlers::response_common,
+    models::{
+        api_key::ApiKeyIdentity, app_dependency::AppDependency, custom_error::CustomError,
+        rate_limit::RateLimitStatus,
+    },
+};
+
+const LIMIT_HEADER: &str = "ratelimit-limit";
+const REMAINING_HEADER: &str = "ratelimit-remaining";
+const RESET_HEADER: &str = "ratelimit-reset";
+
+// Runs before authentication so invalid keys cannot be tried at will
+pub async fn limit_by_ip<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let ip = req.peer_addr().map(|addr| addr.ip().to_string());
+    let status = req
+        .app_data::<Data<AppDependency>>()
+        .zip(ip.as_deref())
+        .and_then(|(data, ip)| data.rate_limit_usecase.check_ip(ip));
+    enforce(req, next, status).await
+}
+
+// Runs after authentication, per key and per route of a key
+pub async fn limit_by_key<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| req.path().to_string());
+    let key = req.extensions().get::<ApiKeyIdentity>().cloned();
+    let status = req
+        .app_data::<Data<AppDependency>>()
+        .zip(key.as_ref())
+        .and_then(|(data, key)| data.rate_limit_usecase.check_key(key, &route));
+    enforce(req, next, status).await
+}
+
+async fn enforce<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+    status: Option<RateLimitStatus>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let Some(status) = status else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+    if status.allowed {
+        let mut res = next.call(req).await?;
+        insert_headers(res.headers_mut(), &status);
+        return Ok(res.map_into_left_body());
+    }
+
+    log::warn!("Rate limited {} {}", req.method(), req.path());
+    let mut response = response_common::create_openai_response::<()>(Err(
+        CustomError::RateLimited(format!("retry in {} seconds", status.reset_secs)),
+    ));
+    insert_headers(response.headers_mut(), &status);
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(status.reset_secs));
+    Ok(req.into_response(response).map_into_right_body())
+}
+
+fn insert_headers(headers: &mut HeaderMap, status: &RateLimitStatus) {
+    for (name, value) in [
+        (LIMIT_HEADER, status.limit as u64),
+        (REMAINING_HEADER, status.remaining as u64),
+        (RESET_HEADER, status.reset_secs),
+    ] {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+    }
+}
@@ -1,14 +1,214 @@
-use actix_web::HttpResponse;
+use actix_web::{
+    http::{header::{HeaderName, HeaderValue}, StatusCode},
+    web::Bytes,
+    HttpResponse,
+};
+use futures_util::{future, stream, Stream, StreamExt};
 use serde_json::json;
 
-use crate::models::custom_error::CustomError;
+use crate::{
+    models::{
+        call_context::{CacheDirective, CallContext},
+        custom_error::CustomError,
+    },
+    utils::sse_utils::SseUtils,
+};
+
+pub const PROVIDER_HEADER: &str = "x-zeus-provider";
+pub const MODEL_HEADER: &str = "x-zeus-model";
+pub const CACHE_HEADER: &str = "x-zeus-cache";
+
+// Tells the caller which provider and model answered, which may differ after a fallback
+pub fn with_route(mut response: HttpResponse, provider: &str, model: &str) -> HttpResponse {
+    for (name, value) in [(PROVIDER_HEADER, provider), (MODEL_HEADER, model)] {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            response
+                .headers_mut()
+                .insert(HeaderName::from_static(name), value);
+        }
+    }
+    response
+}
+
+pub fn with_cache_status(
+    mut response: HttpResponse,
+    context: &CallContext,
+    cached: bool,
+) -> HttpResponse {
+    let status = match (cached, context.cache) {
+        (true, _) => "HIT",
+        (false, CacheDirective::Default) => "MISS",
+        (false, _) => "BYPASS",
+    };
+    response.headers_mut().insert(
+        HeaderName::from_static(CACHE_HEADER),
+        HeaderValue::from_static(status),
+    );
+    response
+}
 
 pub fn create_response<T: serde::Serialize>(result: Result<T, CustomError>) -> HttpResponse {
     match result {
         Ok(response) => HttpResponse::Ok().json(json!({"status": "success", "message": response})),
         Err(e) => {
             log::error!("\n Generating error response: \n {:?} \n", e);
-            HttpResponse::InternalServerError().json(json!({"status": "error", "message": "Something went wrong"}))
+            let status = error_status(&e);
+            let message = error_message(&e, status);
+            HttpResponse::build(status).json(json!({"status": "error", "message": message}))
         },
     }
-}
\ No newline at end of file
+}
+
+// Plain body on success and OpenAI style error object on failure
+pub fn create_openai_response<T: serde::Serialize>(result: Result<T, CustomError>) -> HttpResponse {
+    match result {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => {
+            log::error!("\n Generating error response: \n {:?} \n", e);
+            let status = error_status(&e);
+            let message = error_message(&e, status);
+            HttpResponse::build(status).json(json!({
+                "error": {
+                    "message": message,
+                    "type": error_type(&e),
+                    "code": status.as_u16(),
+                }
+            }))
+        }
+    }
+}
+
+fn error_status(e: &CustomError) -> StatusCode {
+    match e {
+        CustomError::UnknownModel(_) | CustomError::UnknownProvider(_) => StatusCode::NOT_FOUND,
+        CustomError::UnsupportedCapability(_)
+        | CustomError::InvalidRequest(_)
+        | CustomError::ContentBlocked(_) => StatusCode::BAD_REQUEST,
+        CustomError::NonSuccessfulResponse(_) | CustomError::NoContentFromAssistant => {
+            StatusCode::BAD_GATEWAY
+        }
+        CustomError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+        CustomError::CircuitOpen(_) => StatusCode::SERVICE_UNAVAILABLE,
+        CustomError::BudgetExceeded(_) => StatusCode::PAYMENT_REQUIRED,
+        CustomError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+        CustomError::NotFound(_) => StatusCode::NOT_FOUND,
+        CustomError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+        CustomError::SchemaViolation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        CustomError::InternalServerError(status) => *status,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+// Client errors are explained, server side failures stay opaque
+fn error_message(e: &CustomError, status: StatusCode) -> String {
+    if status.is_server_error() {
+        "Something went wrong".to_string()
+    } else {
+        e.to_string()
+    }
+}
+
+fn error_type(e: &CustomError) -> &'static str {
+    match e {
+        CustomError::UnknownModel(_) | CustomError::UnknownProvider(_) => "model_not_found",
+        CustomError::UnsupportedCapability(_) | CustomError::InvalidRequest(_) => {
+            "invalid_request_error"
+        }
+        CustomError::NonSuccessfulResponse(_)
+        | CustomError::NoContentFromAssistant
+        | CustomError::Timeout(_)
+        | CustomError::CircuitOpen(_) => "upstream_error",
+        CustomError::BudgetExceeded(_) => "insufficient_quota",
+        CustomError::Unauthorized(_) => "invalid_api_key",
+        CustomError::NotFound(_) => "not_found_error",
+        CustomError::RateLimited(_) => "rate_limit_exceeded",
+        CustomError::SchemaViolation(_) => "invalid_response_format",
+        CustomError::ContentBlocked(_) => "content_filter",
+        _ => "server_error",
+    }
+}
+
+// Relays audio chunks as they arrive, a failure aborts the response
+pub fn create_audio_response<S>(content_type: &str, audio: S) -> HttpResponse
+where
+    S: Stream<Item = Result<Bytes, CustomError>> + 'static,
+{
+    let body = audio.map(|chunk| {
+        chunk.map_err(|e| {
+            log::error!("\n Aborting audio response: \n {:?} \n", e);
+            actix_web::error::ErrorBadGateway("Something went wrong")
+        })
+    });
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(body)
+}
+
+// Relays serialized events as text/event-stream. The stream ends with `[DONE]`, or with
+// an error event after a failure so clients don't take a partial answer as complete
+pub fn create_sse_response<S>(events: S) -> HttpResponse
+where
+    S: Stream<Item = Result<String, CustomError>> + 'static,
+{
+    let body = events
+        .map(Some)
+        .chain(stream::once(future::ready(None)))
+        .scan(false, |failed, event| {
+            if *failed {
+                return future::ready(None);
+            }
+            let event = match event {
+                Some(Ok(data)) => format!("data: {}\n\n", data),
+                Some(Err(e)) => {
+                    log::error!("\n Generating error event: \n {:?} \n", e);
+                    *failed = true;
+                    format!(
+                        "event: error\ndata: {}\n\n",
+                        json!({"status": "error", "message": "Something went wrong"})
+                    )
+                }
+                None => format!("data: {}\n\n", SseUtils::DONE),
+            };
+            future::ready(Some(event))
+        })
+        .map(|event| Ok::<Bytes, actix_web::Error>(Bytes::from(event)));
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+
+    use super::*;
+
+    async fn sse_body(events: Vec<Result<String, CustomError>>) -> String {
+        let response = create_sse_response(stream::iter(events));
+        let body = to_bytes(response.into_body()).await.ok().unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn sse_response_ends_with_done() {
+        let body = sse_body(vec![Ok("{\"a\":1}".to_string()), Ok("{\"b\":2}".to_string())]).await;
+        assert_eq!(body, "data: {\"a\":1}\n\ndata: {\"b\":2}\n\ndata: [DONE]\n\n");
+    }
+
+    #[actix_web::test]
+    async fn sse_response_stops_after_an_error_without_done() {
+        let body = sse_body(vec![
+            Ok("{\"a\":1}".to_string()),
+            Err(CustomError::NoContentFromAssistant),
+            Ok("{\"b\":2}".to_string()),
+        ])
+        .await;
+        assert_eq!(
+            body,
+            "data: {\"a\":1}\n\nevent: error\ndata: {\"message\":\"Something went wrong\",\"status\":\"error\"}\n\n"
+        );
+    }
+}
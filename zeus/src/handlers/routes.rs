@@ -1,8 +1,9 @@
-use actix_web::{get, web, HttpResponse, Responder};
+use actix_web::{get, middleware::from_fn, web, HttpResponse, Responder};
 use serde_json::json;
 
 use super::{
-    ext_routes,
+    admin_routes, auth_middleware, chat_routes, ext_routes, image_routes,
+    rate_limit_middleware,
     poi_routes::{self},
 };
 
@@ -15,9 +16,40 @@ fn api_router(conf: &mut web::ServiceConfig) {
     conf.service(ping_me);
 }
 
+// The last middleware wrapped runs first: IP limit, then authentication, then key limits
 fn v1_router(conf: &mut web::ServiceConfig) {
-    conf.service(web::scope("/ext").configure(ext_routes::v1_ext_router));
-    conf.service(web::scope("/poi").configure(poi_routes::v1_poi_router));
+    conf.service(
+        web::scope("/ext")
+            .wrap(from_fn(rate_limit_middleware::limit_by_key))
+            .wrap(from_fn(auth_middleware::require_api_key))
+            .wrap(from_fn(rate_limit_middleware::limit_by_ip))
+            .configure(ext_routes::v1_ext_router),
+    );
+    conf.service(
+        web::scope("/poi")
+            .wrap(from_fn(rate_limit_middleware::limit_by_key))
+            .wrap(from_fn(auth_middleware::require_api_key))
+            .wrap(from_fn(rate_limit_middleware::limit_by_ip))
+            .configure(poi_routes::v1_poi_router),
+    );
+    conf.service(
+        web::scope("/chat")
+            .wrap(from_fn(rate_limit_middleware::limit_by_key))
+            .wrap(from_fn(auth_middleware::require_api_key))
+            .wrap(from_fn(rate_limit_middleware::limit_by_ip))
+            .configure(chat_routes::v1_chat_router),
+    );
+    conf.service(
+        web::scope("/images")
+            .wrap(from_fn(rate_limit_middleware::limit_by_ip))
+            .configure(image_routes::v1_images_router),
+    );
+    conf.service(
+        web::scope("/admin")
+            .wrap(from_fn(auth_middleware::require_admin_key))
+            .wrap(from_fn(rate_limit_middleware::limit_by_ip))
+            .configure(admin_routes::v1_admin_router),
+    );
 }
 
 #[get("/ping")]
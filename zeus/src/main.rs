@@ -1,10 +1,12 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use dotenv::dotenv;
 use models::{app_dependency::AppDependency, custom_error::CustomError};
 
 use crate::repository::{
+    api_key_repository::ApiKeyRepository,
     key_value_repository::KeyValueRepository, key_value_vector_repository::KeyValueVectorRepository,
+    usage_repository::UsageRepository,
 };
 
 mod api;
@@ -16,6 +18,9 @@ mod server;
 mod usecase;
 mod utils;
 
+// How often expired generated images and cache entries are removed
+const EVICTION_PERIOD: Duration = Duration::from_secs(600);
+
 #[tokio::main]
 async fn main() -> Result<(), CustomError> {
     println!("Starting Zeus!");
@@ -48,6 +53,16 @@ async fn di() -> Result<AppDependency, CustomError> {
     let pool = Arc::new(db::database_pool::DatabasePool::new(&secrets).await?);
     let vector_store = KeyValueVectorRepository::new(Arc::clone(&pool));
     let key_value_store = KeyValueRepository::new(Arc::clone(&pool));
+    let usage_repository = Arc::new(UsageRepository::new(Arc::clone(&pool)));
+    let usage_usecase = Arc::new(usecase::usage_usecase::UsageUsecase::new(
+        Arc::clone(&usage_repository),
+        &secrets,
+    ));
+    let api_key_usecase = usecase::api_key_usecase::ApiKeyUsecase::new(
+        Arc::new(ApiKeyRepository::new(Arc::clone(&pool))),
+        &secrets,
+    );
+    let rate_limit_usecase = usecase::rate_limit_usecase::RateLimitUsecase::new(&secrets);
     //APIs
     let open_ai_api = Arc::new(api::open_ai::OpenAIApi::new(&secrets));
     let google_vision_api = Arc::new(api::google_vision::GoogleVisionApi::new(&secrets));
@@ -56,12 +71,76 @@ async fn di() -> Result<AppDependency, CustomError> {
     let google_places = Arc::new(api::google_places::GooglePlacesApi::new(&secrets));
     //Storage
     let local_storage = Arc::new(repository::local_storage::LocalStorage::new());
+    if secrets.generated_images_ttl_seconds > 0 {
+        let ttl = Duration::from_secs(secrets.generated_images_ttl_seconds);
+        let storage = Arc::clone(&local_storage);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EVICTION_PERIOD);
+            loop {
+                interval.tick().await;
+                match storage.evict_generated(ttl) {
+                    Ok(0) => {}
+                    Ok(evicted) => log::info!("Removed {} generated images", evicted),
+                    Err(e) => log::error!("Failed to remove generated images: {}", e),
+                }
+            }
+        });
+    }
+    let mut circuits = vec![
+        open_ai_api.circuit(),
+        google_vision_api.circuit(),
+        cloudflare_ai.circuit(),
+        gemini_api.circuit(),
+        google_places.circuit(),
+    ];
+    //LLM providers
+    let mut provider_registry = api::provider_registry::ProviderRegistry::new();
+    provider_registry.register(open_ai_api.clone())?;
+    provider_registry.register(gemini_api.clone())?;
+    provider_registry.register(cloudflare_ai.clone())?;
+    if let Some(anthropic_api) = api::anthropic::AnthropicApi::new(&secrets)? {
+        circuits.push(anthropic_api.circuit());
+        provider_registry.register(Arc::new(anthropic_api))?;
+    }
+    for compatible in &secrets.open_ai_compatible {
+        let compatible_api = api::open_ai::OpenAIApi::compatible(&secrets, compatible);
+        circuits.push(compatible_api.circuit());
+        provider_registry.register(Arc::new(compatible_api))?;
+    }
+    provider_registry.set_fallbacks(secrets.fallback_chains.clone());
+    let provider_registry = Arc::new(provider_registry);
+    //Caches
+    let cache_usecase = Arc::new(usecase::cache_usecase::CacheUsecase::new(
+        Arc::new(key_value_store),
+        Arc::new(vector_store),
+        Arc::clone(&provider_registry),
+        Arc::clone(&usage_usecase),
+        &secrets,
+    ));
+    if secrets.response_cache_ttl_seconds > 0 {
+        let cache = Arc::clone(&cache_usecase);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EVICTION_PERIOD);
+            loop {
+                interval.tick().await;
+                cache.evict_expired().await;
+            }
+        });
+    }
     //Usecases
+    let structured_output_usecase = Arc::new(
+        usecase::structured_output_usecase::StructuredOutputUsecase::new(
+            Arc::clone(&provider_registry),
+            Arc::clone(&usage_usecase),
+            &secrets,
+        ),
+    );
     let openai_usecase = usecase::api_tester_usecase::ExtApiUsecase::new(
-        Arc::clone(&open_ai_api),
+        Arc::clone(&provider_registry),
+        Arc::clone(&usage_usecase),
+        Arc::clone(&cache_usecase),
+        Arc::clone(&structured_output_usecase),
         Arc::clone(&google_vision_api),
-        Arc::clone(&cloudflare_ai),
-        Arc::clone(&gemini_api),
         Arc::clone(&google_places),
         Arc::clone(&local_storage),
     );
@@ -71,10 +150,38 @@ async fn di() -> Result<AppDependency, CustomError> {
         Arc::clone(&gemini_api),
         Arc::clone(&open_ai_api),
         Arc::clone(&google_places),
+        Arc::clone(&usage_usecase),
+    );
+
+    let chat_usecase = usecase::chat_usecase::ChatUsecase::new(
+        Arc::clone(&provider_registry),
+        Arc::clone(&usage_usecase),
+        Arc::clone(&cache_usecase),
+        Arc::clone(&structured_output_usecase),
+    );
+
+    let admin_usecase =
+        usecase::admin_usecase::AdminUsecase::new(
+        circuits,
+        Arc::clone(&usage_usecase),
+        Arc::clone(&cache_usecase),
+    );
+
+    let media_usecase = usecase::media_usecase::MediaUsecase::new(
+        Arc::clone(&open_ai_api),
+        Arc::clone(&cloudflare_ai),
+        Arc::clone(&local_storage),
+        Arc::clone(&usage_usecase),
+        &secrets,
     );
 
     Ok(models::app_dependency::AppDependency::new(
         openai_usecase,
         poi_usecase,
+        chat_usecase,
+        admin_usecase,
+        api_key_usecase,
+        rate_limit_usecase,
+        media_usecase,
     ))
 }
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::{models::rate_limit::RateLimit, repository::api_key_repository::ApiKeyEntity};
+
+// Authenticated caller, stored in the request extensions by the API key middleware
+#[derive(Debug, Clone)]
+pub struct ApiKeyIdentity {
+    pub id: i32,
+    pub name: String,
+    pub project: String,
+    pub rate_limit: Option<RateLimit>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub project: Option<String>,
+    pub metadata: Option<JsonValue>,
+    // Replaces the default key rate limit
+    pub rate_limit: Option<RateLimit>,
+}
+
+// The plain key is only ever returned here, at creation
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    pub key: String,
+    #[serde(flatten)]
+    pub entity: ApiKeyEntity,
+}
@@ -1,8 +1,16 @@
-use crate::usecase::{api_tester_usecase, poi_usecase};
+use crate::usecase::{
+    admin_usecase, api_key_usecase, api_tester_usecase, chat_usecase, media_usecase,
+    poi_usecase, rate_limit_usecase,
+};
 
 pub struct AppDependency {
     pub ext_api_usecase: api_tester_usecase::ExtApiUsecase,
     pub poi_usecase: poi_usecase::PoiUsecase,
+    pub chat_usecase: chat_usecase::ChatUsecase,
+    pub admin_usecase: admin_usecase::AdminUsecase,
+    pub api_key_usecase: api_key_usecase::ApiKeyUsecase,
+    pub rate_limit_usecase: rate_limit_usecase::RateLimitUsecase,
+    pub media_usecase: media_usecase::MediaUsecase,
 }
 
 impl AppDependency {
@@ -10,10 +18,20 @@ impl AppDependency {
     pub fn new(
         ext_api_usecase: api_tester_usecase::ExtApiUsecase,
         poi_usecase: poi_usecase::PoiUsecase,
+        chat_usecase: chat_usecase::ChatUsecase,
+        admin_usecase: admin_usecase::AdminUsecase,
+        api_key_usecase: api_key_usecase::ApiKeyUsecase,
+        rate_limit_usecase: rate_limit_usecase::RateLimitUsecase,
+        media_usecase: media_usecase::MediaUsecase,
     ) -> AppDependency {
         Self {
             ext_api_usecase,
             poi_usecase,
+            chat_usecase,
+            admin_usecase,
+            api_key_usecase,
+            rate_limit_usecase,
+            media_usecase,
         }
     }
 }
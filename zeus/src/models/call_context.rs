@@ -0,0 +1,67 @@
+use actix_web::{http::header::CACHE_CONTROL, HttpMessage, HttpRequest};
+
+use crate::models::api_key::ApiKeyIdentity;
+
+// Who and what a gateway call is made for, attached to usage records
+#[derive(Debug, Clone)]
+pub struct CallContext {
+    pub endpoint: String,
+    pub project: String,
+    pub api_key: Option<ApiKeyIdentity>,
+    pub cache: CacheDirective,
+}
+
+// Request side Cache-Control: `no-cache` skips the lookup, `no-store` skips the cache entirely
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheDirective {
+    Default,
+    NoCache,
+    NoStore,
+}
+
+impl CacheDirective {
+    fn new(req: &HttpRequest) -> Self {
+        let directives = req
+            .headers()
+            .get(CACHE_CONTROL)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_lowercase();
+        let has = |directive: &str| directives.split(',').any(|value| value.trim() == directive);
+        if has("no-store") {
+            Self::NoStore
+        } else if has("no-cache") {
+            Self::NoCache
+        } else {
+            Self::Default
+        }
+    }
+}
+
+impl CallContext {
+    pub const PROJECT_HEADER: &'static str = "X-Zeus-Project";
+    pub const DEFAULT_PROJECT: &'static str = "default";
+
+    // Keys are bound to a project, the project header only applies to unauthenticated routes
+    pub fn new(req: &HttpRequest) -> Self {
+        let api_key = req.extensions().get::<ApiKeyIdentity>().cloned();
+        let project = match &api_key {
+            Some(key) => key.project.clone(),
+            None => req
+                .headers()
+                .get(Self::PROJECT_HEADER)
+                .and_then(|project| project.to_str().ok())
+                .filter(|project| !project.is_empty())
+                .unwrap_or(Self::DEFAULT_PROJECT)
+                .to_string(),
+        };
+        Self {
+            endpoint: req
+                .match_pattern()
+                .unwrap_or_else(|| req.path().to_string()),
+            project,
+            api_key,
+            cache: CacheDirective::new(req),
+        }
+    }
+}
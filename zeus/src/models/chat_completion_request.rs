@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::llm_provider::{ChatRequest, ChatResponse},
+    models::{
+        chat_message::{ChatMessage, ChatRole},
+        response_format::ResponseFormat,
+        safety::{SafetyRating, SafetySetting},
+        sampling_params::SamplingParams,
+        tool::{Tool, ToolChoice},
+    },
+};
+
+// OpenAI compatible request & response shapes for /v1/chat/completions
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(flatten)]
+    pub params: SamplingParams,
+    pub stream: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<Tool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub safety_settings: Vec<SafetySetting>,
+}
+
+impl ChatCompletionRequest {
+    pub fn chat_request(&self) -> ChatRequest {
+        ChatRequest {
+            model: self.model.clone(),
+            messages: self.messages.clone(),
+            params: self.params.clone(),
+            tools: self.tools.clone(),
+            tool_choice: self.tool_choice.clone(),
+            response_format: self.response_format.clone(),
+            safety_settings: self.safety_settings.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatCompletionResponse {
+    // Answering provider, returned as a header rather than in the OpenAI body
+    #[serde(skip)]
+    pub provider: String,
+    #[serde(skip)]
+    pub cached: bool,
+    pub id: String,
+    pub object: String,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<ChatCompletionChoice>,
+    pub usage: ChatCompletionUsage,
+    // Gemini ratings, not part of the OpenAI schema
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub safety_ratings: Vec<SafetyRating>,
+}
+
+impl ChatCompletionResponse {
+    pub fn new(response: ChatResponse) -> Self {
+        let usage = response.usage.unwrap_or_default();
+        Self {
+            provider: response.provider,
+            cached: response.cached,
+            id: format!("chatcmpl-{}", ulid::Ulid::new()),
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp(),
+            model: response.model,
+            choices: vec![ChatCompletionChoice {
+                index: 0,
+                message: ChatMessage {
+                    tool_calls: response.tool_calls,
+                    ..ChatMessage::new(ChatRole::Assistant, response.content)
+                },
+                finish_reason: response.finish_reason.unwrap_or("stop".to_string()),
+            }],
+            usage: ChatCompletionUsage {
+                prompt_tokens: usage.prompt_tokens,
+                completion_tokens: usage.completion_tokens,
+                total_tokens: usage.total_tokens,
+            },
+            safety_ratings: response.safety_ratings,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatCompletionChoice {
+    pub index: u32,
+    pub message: ChatMessage,
+    pub finish_reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatCompletionUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    pub object: String,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<ChatCompletionChunkChoice>,
+}
+
+impl ChatCompletionChunk {
+    pub fn new(
+        id: &str,
+        created: i64,
+        model: &str,
+        content: Option<String>,
+        finish_reason: Option<String>,
+    ) -> Self {
+        Self {
+            id: id.to_string(),
+            object: "chat.completion.chunk".to_string(),
+            created,
+            model: model.to_string(),
+            choices: vec![ChatCompletionChunkChoice {
+                index: 0,
+                delta: ChatCompletionDelta {
+                    role: content.as_ref().map(|_| ChatRole::Assistant),
+                    content,
+                },
+                finish_reason,
+            }],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatCompletionChunkChoice {
+    pub index: u32,
+    pub delta: ChatCompletionDelta,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatCompletionDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<ChatRole>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::models::tool::ToolCall;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+    Tool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub content: String,
+    // Calls requested by an assistant turn
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    // The call a tool turn answers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: String) -> Self {
+        Self {
+            role,
+            content,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+
+    // Name of the function a tool turn answers, looked up in the preceding assistant turns
+    pub fn tool_name<'a>(&self, history: &'a [ChatMessage]) -> Option<&'a str> {
+        let id = self.tool_call_id.as_deref()?;
+        history
+            .iter()
+            .flat_map(|message| &message.tool_calls)
+            .find(|call| call.id == id)
+            .map(|call| call.function.name.as_str())
+    }
+}
+
+// Assistant turns carrying tool calls come with `"content": null`
+fn null_as_empty<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
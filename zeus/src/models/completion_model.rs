@@ -1,7 +1,39 @@
 use serde::{Deserialize, Serialize};
 
+use crate::models::{
+    chat_message::{ChatMessage, ChatRole},
+    custom_error::CustomError,
+    response_format::ResponseFormat,
+    safety::SafetySetting,
+    sampling_params::SamplingParams,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompletionRequest {
-    pub query: String,
-}
\ No newline at end of file
+    pub query: Option<String>,
+    pub messages: Option<Vec<ChatMessage>>,
+    pub model: Option<String>,
+    #[serde(flatten)]
+    pub params: SamplingParams,
+    pub stream: Option<bool>,
+    pub response_format: Option<ResponseFormat>,
+    #[serde(default)]
+    pub safety_settings: Vec<SafetySetting>,
+}
+
+impl CompletionRequest {
+    // Conversation history followed by the query as the latest user turn
+    pub fn history(&self) -> Result<Vec<ChatMessage>, CustomError> {
+        let mut history = self.messages.clone().unwrap_or_default();
+        if let Some(query) = &self.query {
+            history.push(ChatMessage::new(ChatRole::User, query.clone()));
+        }
+        if history.iter().any(|message| message.role == ChatRole::User) {
+            Ok(history)
+        } else {
+            Err(CustomError::InvalidRequest(
+                "conversation must contain a user message".to_string(),
+            ))
+        }
+    }
+}
@@ -10,6 +10,31 @@ pub enum CustomError {
     File(String),
     InternalServerError(StatusCode),
     SqlError(sqlx::Error),
+    UnsupportedCapability(String),
+    UnknownProvider(String),
+    UnknownModel(String),
+    InvalidRequest(String),
+    Timeout(String),
+    CircuitOpen(String),
+    BudgetExceeded(String),
+    Unauthorized(String),
+    NotFound(String),
+    RateLimited(String),
+    SchemaViolation(String),
+    ContentBlocked(String),
+    Configuration(String),
+}
+
+impl CustomError {
+    // Upstream failures that another provider may not share: rate limits, outages and timeouts
+    pub fn is_transient(&self) -> bool {
+        match self {
+            CustomError::NonSuccessfulResponse(code) => *code == 429 || *code >= 500,
+            CustomError::HttpRequestError(e) => e.is_timeout() || e.is_connect(),
+            CustomError::Timeout(_) | CustomError::CircuitOpen(_) => true,
+            _ => false,
+        }
+    }
 }
 
 impl std::fmt::Display for CustomError {
@@ -27,6 +52,19 @@ impl std::fmt::Display for CustomError {
             CustomError::File(e) => write!(f, "File error: {}", e),
             CustomError::InternalServerError(e) => write!(f, "HttpError: {}", e),
             CustomError::SqlError(e) => write!(f, "SqlError: {}", e),
+            CustomError::UnsupportedCapability(e) => write!(f, "UnsupportedCapability: {}", e),
+            CustomError::UnknownProvider(name) => write!(f, "UnknownProvider: {}", name),
+            CustomError::UnknownModel(name) => write!(f, "UnknownModel: {}", name),
+            CustomError::InvalidRequest(e) => write!(f, "InvalidRequest: {}", e),
+            CustomError::Timeout(e) => write!(f, "Timeout: {}", e),
+            CustomError::CircuitOpen(name) => write!(f, "CircuitOpen: {}", name),
+            CustomError::BudgetExceeded(e) => write!(f, "BudgetExceeded: {}", e),
+            CustomError::Unauthorized(e) => write!(f, "Unauthorized: {}", e),
+            CustomError::NotFound(e) => write!(f, "NotFound: {}", e),
+            CustomError::RateLimited(e) => write!(f, "RateLimited: {}", e),
+            CustomError::SchemaViolation(e) => write!(f, "SchemaViolation: {}", e),
+            CustomError::ContentBlocked(e) => write!(f, "ContentBlocked: {}", e),
+            CustomError::Configuration(e) => write!(f, "Configuration: {}", e),
         }
     }
 }
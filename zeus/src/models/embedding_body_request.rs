@@ -4,4 +4,10 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EmbeddintBodyRequest {
     text: Vec<String>,
+}
+
+impl EmbeddintBodyRequest {
+    pub fn new(text: Vec<String>) -> Self {
+        Self { text }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::custom_error::CustomError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageGenerationRequest {
+    pub prompt: String,
+    // Provider default when missing
+    pub model: Option<String>,
+    #[serde(default = "ImageGenerationRequest::default_n")]
+    pub n: u32,
+    // `{width}x{height}`, e.g. `1024x1024`
+    pub size: Option<String>,
+    #[serde(default)]
+    pub response_format: ImageResponseFormat,
+}
+
+impl ImageGenerationRequest {
+    pub const DEFAULT_SIZE: &'static str = "1024x1024";
+
+    fn default_n() -> u32 {
+        1
+    }
+
+    pub fn size(&self) -> &str {
+        self.size.as_deref().unwrap_or(Self::DEFAULT_SIZE)
+    }
+
+    pub fn dimensions(&self) -> Result<(u32, u32), CustomError> {
+        self.size()
+            .split_once('x')
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+            .ok_or_else(|| {
+                CustomError::InvalidRequest(format!(
+                    "size must look like {}, got {}",
+                    Self::DEFAULT_SIZE,
+                    self.size()
+                ))
+            })
+    }
+
+    pub fn validate(&self, max_n: u32) -> Result<(), CustomError> {
+        if self.prompt.trim().is_empty() {
+            return Err(CustomError::InvalidRequest("prompt must not be empty".to_string()));
+        }
+        if self.n == 0 || self.n > max_n {
+            return Err(CustomError::InvalidRequest(format!(
+                "n must be between 1 and {}",
+                max_n
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageResponseFormat {
+    #[default]
+    Url,
+    B64Json,
+}
+
+// PNG bytes as returned by a provider
+pub struct GeneratedImageData {
+    pub bytes: Vec<u8>,
+    pub revised_prompt: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageGenerationResponse {
+    pub provider: String,
+    pub model: String,
+    pub created: i64,
+    pub data: Vec<GeneratedImage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GeneratedImage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub b64_json: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revised_prompt: Option<String>,
+}
@@ -3,4 +3,18 @@ pub mod custom_error;
 pub mod completion_model;
 pub mod vision_request;
 pub mod file_upload_request;
-pub mod embedding_body_request;
\ No newline at end of file
+pub mod embedding_body_request;
+pub mod chat_message;
+pub mod chat_completion_request;
+pub mod sampling_params;
+pub mod call_context;
+pub mod usage_query;
+pub mod api_key;
+pub mod rate_limit;
+pub mod tool;
+pub mod response_format;
+pub mod safety;
+pub mod transcription;
+pub mod speech_request;
+pub mod image_generation;
+pub mod translation;
\ No newline at end of file
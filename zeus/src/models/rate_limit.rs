@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+// Token bucket refilled at `requests_per_minute`, holding up to `burst` requests
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub requests_per_minute: u32,
+    pub burst: u32,
+}
+
+// Outcome of a rate limit check, reported in the RateLimit-* headers
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitStatus {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset_secs: u64,
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+// OpenAI style `response_format`, JSON answers are validated before they are returned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    JsonObject,
+    JsonSchema { json_schema: JsonSchemaFormat },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    pub name: String,
+    pub schema: JsonValue,
+}
+
+impl ResponseFormat {
+    pub fn is_json(&self) -> bool {
+        !matches!(self, ResponseFormat::Text)
+    }
+
+    pub fn schema(&self) -> Option<&JsonValue> {
+        match self {
+            ResponseFormat::JsonSchema { json_schema } => Some(&json_schema.schema),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SamplingParams {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<u32>,
+    pub max_tokens: Option<u32>,
+    #[serde(default, deserialize_with = "stop_sequences")]
+    pub stop: Option<Vec<String>>,
+    pub seed: Option<u64>,
+}
+
+// OpenAI accepts `stop` either as a single string or as a list
+fn stop_sequences<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stop {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Option::<Stop>::deserialize(deserializer)? {
+        Some(Stop::One(stop)) => Some(vec![stop]),
+        Some(Stop::Many(stop)) => Some(stop),
+        None => None,
+    })
+}
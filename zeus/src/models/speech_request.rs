@@ -0,0 +1,78 @@
+use actix_web::web::Bytes;
+use futures_util::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+
+use crate::models::custom_error::CustomError;
+
+// Audio chunks relayed as they are synthesized
+pub type AudioStream = BoxStream<'static, Result<Bytes, CustomError>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeechRequest {
+    pub input: String,
+    #[serde(default)]
+    pub voice: SpeechVoice,
+    #[serde(default)]
+    pub format: SpeechFormat,
+    pub speed: Option<f32>,
+}
+
+impl SpeechRequest {
+    const MAX_INPUT_CHARS: usize = 4096;
+    const MIN_SPEED: f32 = 0.25;
+    const MAX_SPEED: f32 = 4.0;
+
+    pub fn validate(&self) -> Result<(), CustomError> {
+        if self.input.trim().is_empty() {
+            return Err(CustomError::InvalidRequest("input must not be empty".to_string()));
+        }
+        if self.input.chars().count() > Self::MAX_INPUT_CHARS {
+            return Err(CustomError::InvalidRequest(format!(
+                "input must be at most {} characters",
+                Self::MAX_INPUT_CHARS
+            )));
+        }
+        if let Some(speed) = self.speed {
+            if !(Self::MIN_SPEED..=Self::MAX_SPEED).contains(&speed) {
+                return Err(CustomError::InvalidRequest(format!(
+                    "speed must be between {} and {}",
+                    Self::MIN_SPEED,
+                    Self::MAX_SPEED
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeechVoice {
+    #[default]
+    Alloy,
+    Echo,
+    Fable,
+    Onyx,
+    Nova,
+    Shimmer,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeechFormat {
+    #[default]
+    Mp3,
+    Opus,
+    Wav,
+}
+
+impl SpeechFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SpeechFormat::Mp3 => "audio/mpeg",
+            // Opus comes in an Ogg container
+            SpeechFormat::Opus => "audio/ogg",
+            SpeechFormat::Wav => "audio/wav",
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+// Provider neutral tool calling, shaped after the OpenAI API the gateway exposes
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tool {
+    #[serde(rename = "type", default = "function_type")]
+    pub type_: String,
+    pub function: FunctionDefinition,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionDefinition {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // JSON schema of the arguments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<JsonValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type", default = "function_type")]
+    pub type_: String,
+    pub function: FunctionCall,
+}
+
+impl ToolCall {
+    pub fn new(name: &str, arguments: &JsonValue) -> Self {
+        Self {
+            id: format!("call_{}", ulid::Ulid::new()),
+            type_: function_type(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    // JSON encoded, as produced by the model
+    pub arguments: String,
+}
+
+// `none`, `auto`, `required` or a single named function
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ToolChoice {
+    Mode(ToolChoiceMode),
+    Function {
+        #[serde(rename = "type", default = "function_type")]
+        type_: String,
+        function: ToolChoiceFunction,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolChoiceMode {
+    None,
+    Auto,
+    Required,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolChoiceFunction {
+    pub name: String,
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
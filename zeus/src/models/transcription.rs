@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Transcription {
+    pub provider: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    // Seconds from the start of the audio
+    pub segments: Vec<TranscriptionSegment>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TranscriptionSegment {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
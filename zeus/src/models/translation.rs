@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::custom_error::CustomError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslationRequest {
+    pub text: String,
+    // ISO 639 codes, e.g. `en`, `el`
+    pub source_lang: String,
+    pub target_lang: String,
+    // LLM used for pairs m2m100 can't translate, OpenAI when missing
+    pub fallback_provider: Option<String>,
+}
+
+impl TranslationRequest {
+    pub fn validate(&self) -> Result<(), CustomError> {
+        if self.text.trim().is_empty() {
+            return Err(CustomError::InvalidRequest("text must not be empty".to_string()));
+        }
+        for lang in [&self.source_lang, &self.target_lang] {
+            if !(2..=3).contains(&lang.len()) || !lang.chars().all(|c| c.is_ascii_lowercase()) {
+                return Err(CustomError::InvalidRequest(format!(
+                    "{} is not an ISO 639 language code",
+                    lang
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TranslationResponse {
+    pub provider: String,
+    pub model: String,
+    pub source_lang: String,
+    pub target_lang: String,
+    pub translated_text: String,
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use sqlx::types::Json;
+
+use crate::{
+    db::database_pool::DatabasePool,
+    models::{custom_error::CustomError, rate_limit::RateLimit},
+};
+
+// The key hash never leaves the repository
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct ApiKeyEntity {
+    pub id: i32,
+    pub name: String,
+    pub key_prefix: String,
+    pub project: String,
+    pub metadata: JsonValue,
+    pub rate_limit: Option<Json<RateLimit>>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+pub struct ApiKeyRepository {
+    db: Arc<DatabasePool>,
+}
+
+impl ApiKeyRepository {
+    const COLUMNS: &'static str =
+        "id, name, key_prefix, project, metadata, rate_limit, created_at, last_used_at, revoked_at";
+
+    pub fn new(db: Arc<DatabasePool>) -> Self {
+        Self { db }
+    }
+
+    pub async fn insert_one(
+        &self,
+        name: &str,
+        key_prefix: &str,
+        key_hash: &str,
+        project: &str,
+        metadata: JsonValue,
+        rate_limit: Option<RateLimit>,
+    ) -> Result<ApiKeyEntity, CustomError> {
+        let query_str = format!(
+            "INSERT INTO api_keys (name, key_prefix, key_hash, project, metadata, rate_limit) \
+             VALUES ($1, $2, $3, $4, $5, $6) RETURNING {}",
+            Self::COLUMNS
+        );
+        let entity = sqlx::query_as::<_, ApiKeyEntity>(&query_str)
+            .bind(name)
+            .bind(key_prefix)
+            .bind(key_hash)
+            .bind(project)
+            .bind(metadata)
+            .bind(rate_limit.map(Json))
+            .fetch_one(self.db.pool())
+            .await?;
+
+        Ok(entity)
+    }
+
+    pub async fn fetch_many(&self) -> Result<Vec<ApiKeyEntity>, CustomError> {
+        let query_str = format!("SELECT {} FROM api_keys ORDER BY id", Self::COLUMNS);
+        let entities = sqlx::query_as::<_, ApiKeyEntity>(&query_str)
+            .fetch_all(self.db.pool())
+            .await?;
+
+        Ok(entities)
+    }
+
+    // Looks up an active key and marks it as used in the same round trip
+    pub async fn touch_active_by_hash(
+        &self,
+        key_hash: &str,
+    ) -> Result<Option<ApiKeyEntity>, CustomError> {
+        let query_str = format!(
+            "UPDATE api_keys SET last_used_at = CURRENT_TIMESTAMP \
+             WHERE key_hash = $1 AND revoked_at IS NULL RETURNING {}",
+            Self::COLUMNS
+        );
+        let entity = sqlx::query_as::<_, ApiKeyEntity>(&query_str)
+            .bind(key_hash)
+            .fetch_optional(self.db.pool())
+            .await?;
+
+        Ok(entity)
+    }
+
+    pub async fn revoke(&self, id: i32) -> Result<Option<ApiKeyEntity>, CustomError> {
+        let query_str = format!(
+            "UPDATE api_keys SET revoked_at = COALESCE(revoked_at, CURRENT_TIMESTAMP) \
+             WHERE id = $1 RETURNING {}",
+            Self::COLUMNS
+        );
+        let entity = sqlx::query_as::<_, ApiKeyEntity>(&query_str)
+            .bind(id)
+            .fetch_optional(self.db.pool())
+            .await?;
+
+        Ok(entity)
+    }
+}
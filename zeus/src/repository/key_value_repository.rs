@@ -69,4 +69,47 @@ impl KeyValueRepository {
         }
         Ok(ids)
     }
+
+    // Entries past their expiry are treated as missing
+    pub async fn fetch_by_key(&self, key: &str) -> sqlx::Result<Option<JsonValue>> {
+        let json_body = sqlx::query_scalar(
+            "SELECT json_body FROM key_value_store \
+             WHERE key = $1 AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)",
+        )
+        .bind(key)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(json_body)
+    }
+
+    pub async fn upsert_by_key(
+        &self,
+        key: &str,
+        json_body: JsonValue,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> sqlx::Result<i32> {
+        let id = sqlx::query_scalar(
+            "INSERT INTO key_value_store (key, json_body, expires_at) VALUES ($1, $2, $3) \
+             ON CONFLICT (key) DO UPDATE SET json_body = EXCLUDED.json_body, expires_at = EXCLUDED.expires_at \
+             RETURNING id",
+        )
+        .bind(key)
+        .bind(json_body)
+        .bind(expires_at)
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok(id)
+    }
+
+    pub async fn delete_expired(&self) -> sqlx::Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM key_value_store WHERE expires_at <= CURRENT_TIMESTAMP",
+        )
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(result.rows_affected())
+    }
 }
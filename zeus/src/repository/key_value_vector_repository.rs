@@ -1,20 +1,11 @@
 use std::sync::Arc;
 
-use chrono::{DateTime, Utc};
+use pgvector::Vector;
 use serde_json::Value as JsonValue;
-use sqlx::{FromRow, PgPool, Row};
+use sqlx::Row;
 
 use crate::{db::database_pool::DatabasePool, models::custom_error::CustomError};
 
-#[derive(Debug, FromRow)]
-pub struct KeyValueVectorEntity {
-    id: i32,
-    vector_data: Vec<f32>,
-    metadata: JsonValue,
-    created_at: DateTime<Utc>,
-    updated_at: DateTime<Utc>,
-}
-
 pub struct KeyValueVectorRepository {
     db: Arc<DatabasePool>,
 }
@@ -32,7 +23,7 @@ impl KeyValueVectorRepository {
         let query_str =
             "INSERT INTO key_value_vector (vector_data, metadata) VALUES ($1, $2) RETURNING id";
         let row = sqlx::query(query_str)
-            .bind(vector_data)
+            .bind(Vector::from(vector_data))
             .bind(metadata)
             .fetch_one(self.db.pool())
             .await?;
@@ -41,40 +32,39 @@ impl KeyValueVectorRepository {
         Ok(id)
     }
 
+    // Closest unexpired entry of a scope within `max_distance` (L2), with its distance
     pub async fn search_by_distance(
         &self,
         vector: Vec<f32>,
-        limit: i64,
-    ) -> Result<Vec<KeyValueVectorEntity>, CustomError> {
-        let query_str = "SELECT * FROM key_value_vector ORDER BY vector_data <-> $1 LIMIT $2";
-        let rows = sqlx::query_as::<_, KeyValueVectorEntity>(query_str)
-            .bind(vector)
-            .bind(limit)
-            .fetch_all(self.db.pool())
+        scope: &str,
+        max_distance: f64,
+    ) -> Result<Option<(JsonValue, f64)>, CustomError> {
+        let query_str = "
+            SELECT metadata, vector_data <-> $1 AS distance FROM key_value_vector
+            WHERE metadata->>'scope' = $2
+              AND (metadata->>'expires_at')::timestamptz > CURRENT_TIMESTAMP
+              AND vector_data <-> $1 <= $3
+            ORDER BY vector_data <-> $1
+            LIMIT 1";
+        let row = sqlx::query(query_str)
+            .bind(Vector::from(vector))
+            .bind(scope)
+            .bind(max_distance)
+            .fetch_optional(self.db.pool())
             .await?;
 
-        Ok(rows)
+        match row {
+            Some(row) => Ok(Some((row.try_get("metadata")?, row.try_get("distance")?))),
+            None => Ok(None),
+        }
     }
 
-    pub async fn find_nearest_neighbors(
-        &self,
-        reference_id: i32,
-        limit: i64,
-    ) -> Result<Vec<KeyValueVectorEntity>, CustomError> {
-        let query_str = "
-            SELECT * FROM key_value_vector 
-            WHERE id != $1 
-            ORDER BY vector_data <-> (
-                SELECT vector_data FROM key_value_vector WHERE id = $1
-            ) 
-            LIMIT $2";
-
-        let rows = sqlx::query_as::<_, KeyValueVectorEntity>(query_str)
-            .bind(reference_id)
-            .bind(limit)
-            .fetch_all(self.db.pool())
-            .await?;
+    // Entries without an `expires_at` in their metadata are kept
+    pub async fn delete_expired(&self) -> Result<u64, CustomError> {
+        let query_str = "DELETE FROM key_value_vector \
+            WHERE (metadata->>'expires_at')::timestamptz <= CURRENT_TIMESTAMP";
+        let result = sqlx::query(query_str).execute(self.db.pool()).await?;
 
-        Ok(rows)
+        Ok(result.rows_affected())
     }
 }
@@ -1,7 +1,11 @@
-use std::fs::{self};
+use std::{
+    fs::{self},
+    time::{Duration, SystemTime},
+};
 
 use actix_multipart::form::tempfile;
 use chrono::{Utc, DateTime};
+use rand::RngCore;
 
 use crate::models::custom_error::CustomError;
 
@@ -9,10 +13,14 @@ pub struct LocalStorage;
 
 impl LocalStorage {
     const UPLOAD_FOLDER: &'static str = "./target/cache/uploads";
+    // Kept apart from uploads as it is served back to clients
+    const GENERATED_FOLDER: &'static str = "./target/cache/generated";
 
     pub fn new() -> Self {
-        fs::create_dir_all(Self::UPLOAD_FOLDER)
-            .expect(format!("Unable to create upload folder: {}", Self::UPLOAD_FOLDER).as_str());
+        for folder in [Self::UPLOAD_FOLDER, Self::GENERATED_FOLDER] {
+            fs::create_dir_all(folder)
+                .expect(format!("Unable to create folder: {}", folder).as_str());
+        }
         Self {}
     }
 
@@ -33,6 +41,45 @@ impl LocalStorage {
         }
     }
 
+    // Returns the generated file name, random as it is served without authentication
+    pub fn save_generated(&self, bytes: &[u8], extension: &str) -> Result<String, CustomError> {
+        let mut name = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut name);
+        let file_name = format!("{}.{}", hex::encode(name), extension);
+        let path = format!("{}/{}", Self::GENERATED_FOLDER, file_name);
+        log::info!("saving to {path}", path = path);
+        fs::write(&path, bytes)
+            .map_err(|e| CustomError::File(format!("Error saving file: {}", e)))
+            .map(|_| file_name)
+    }
+
+    pub fn generated(&self, file_name: &str) -> Result<Vec<u8>, CustomError> {
+        if file_name.contains(['/', '\\']) || file_name.starts_with('.') {
+            return Err(CustomError::NotFound(file_name.to_string()));
+        }
+        fs::read(format!("{}/{}", Self::GENERATED_FOLDER, file_name))
+            .map_err(|_| CustomError::NotFound(file_name.to_string()))
+    }
+
+    // Removes generated files older than `ttl`, returns how many were removed
+    pub fn evict_generated(&self, ttl: Duration) -> Result<usize, CustomError> {
+        let mut evicted = 0;
+        for entry in fs::read_dir(Self::GENERATED_FOLDER).map_err(CustomError::IoError)? {
+            let entry = entry.map_err(CustomError::IoError)?;
+            let expired = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age > ttl);
+            if expired {
+                fs::remove_file(entry.path()).map_err(CustomError::IoError)?;
+                evicted += 1;
+            }
+        }
+        Ok(evicted)
+    }
+
     pub fn folder(&self) -> &'static str {
         Self::UPLOAD_FOLDER
     }
@@ -2,4 +2,6 @@ pub mod secrets;
 pub mod local_storage;
 pub mod prompt_provider;
 pub mod key_value_repository;
-pub mod key_value_vector_repository;
\ No newline at end of file
+pub mod key_value_vector_repository;
+pub mod usage_repository;
+pub mod api_key_repository;
\ No newline at end of file
@@ -3,6 +3,7 @@ pub enum Prompt {
     Poi,
     Ocr,
     Compact,
+    Translation,
 }
 
 impl Prompt {
@@ -19,6 +20,9 @@ impl Prompt {
             },
             Prompt::Compact => {
                 include_str!("../../prompt/compact.txt").to_string()
+            },
+            Prompt::Translation => {
+                include_str!("../../prompt/translation.txt").to_string()
             }
         }
     }
@@ -1,9 +1,36 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::models::{rate_limit::RateLimit, safety::SafetySetting};
+
+#[cfg_attr(test, derive(Default))]
 pub struct Secrets {
     pub open_ai_api_key: String,
     pub cloudflare_api_key: String,
     pub cloudflare_account: String,
     pub google_vision_api_key: String,
     pub google_ai_studio_api_key: String,
+    pub anthropic_api_key: Option<String>,
+    pub anthropic_base_url: String,
+    pub open_ai_compatible: Vec<OpenAiCompatibleSecrets>,
+    pub fallback_chains: HashMap<String, Vec<String>>,
+    pub retry_max_attempts: u32,
+    pub retry_base_delay_ms: u64,
+    pub retry_max_delay_ms: u64,
+    pub provider_timeouts: HashMap<String, TimeoutSecrets>,
+    pub circuit_failure_threshold: u32,
+    pub circuit_cool_down_ms: u64,
+    pub spend_budgets: HashMap<String, BudgetSecrets>,
+    pub response_cache_ttl_seconds: u64,
+    pub semantic_cache_max_distance: Option<f64>,
+    pub admin_api_key: Option<String>,
+    pub rate_limits: RateLimitSecrets,
+    pub structured_output_max_retries: u32,
+    pub gemini_safety_settings: Vec<SafetySetting>,
+    pub transcription_max_duration_secs: u32,
+    pub public_base_url: String,
+    pub generated_images_ttl_seconds: u64,
     pub rds_hostname: String,
     pub rds_port: i32,
     pub rds_db_name: String,
@@ -23,6 +50,110 @@ impl Secrets {
             std::env::var("CLOUDFLARE_ACCOUNT").expect("CLOUDFLARE_ACCOUNT must be set.");
         let google_ai_studio_api_key =
             std::env::var("GOOGLE_PALM2_API_KEY").expect("GOOGLE_PALM2_API_KEY must be set.");
+        let anthropic_api_key = std::env::var("ANTHROPIC_API_KEY").ok();
+        let anthropic_base_url = std::env::var("ANTHROPIC_BASE_URL")
+            .unwrap_or("https://api.anthropic.com".to_string());
+        let open_ai_compatible: Vec<OpenAiCompatibleSecrets> =
+            std::env::var("OPENAI_COMPATIBLE_PROVIDERS")
+                .map(|providers| {
+                    serde_json::from_str(&providers)
+                        .expect("OPENAI_COMPATIBLE_PROVIDERS must be a JSON list of providers.")
+                })
+                .unwrap_or_default();
+        for compatible in &open_ai_compatible {
+            assert!(
+                !compatible.models.is_empty(),
+                "OPENAI_COMPATIBLE_PROVIDERS provider {} must list at least one model.",
+                compatible.name
+            );
+        }
+        let fallback_chains = std::env::var("FALLBACK_CHAINS")
+            .map(|chains| {
+                serde_json::from_str(&chains)
+                    .expect("FALLBACK_CHAINS must be a JSON map of model to fallback models.")
+            })
+            .unwrap_or_default();
+        let retry_max_attempts = std::env::var("RETRY_MAX_ATTEMPTS")
+            .map(|attempts| attempts.parse::<u32>().expect("RETRY_MAX_ATTEMPTS must be a number."))
+            .unwrap_or(3);
+        let retry_base_delay_ms = std::env::var("RETRY_BASE_DELAY_MS")
+            .map(|delay| delay.parse::<u64>().expect("RETRY_BASE_DELAY_MS must be a number."))
+            .unwrap_or(500);
+        let retry_max_delay_ms = std::env::var("RETRY_MAX_DELAY_MS")
+            .map(|delay| delay.parse::<u64>().expect("RETRY_MAX_DELAY_MS must be a number."))
+            .unwrap_or(10_000);
+        let provider_timeouts = std::env::var("PROVIDER_TIMEOUTS")
+            .map(|timeouts| {
+                serde_json::from_str(&timeouts)
+                    .expect("PROVIDER_TIMEOUTS must be a JSON map of provider to timeouts.")
+            })
+            .unwrap_or_default();
+        let circuit_failure_threshold = std::env::var("CIRCUIT_FAILURE_THRESHOLD")
+            .map(|failures| {
+                failures
+                    .parse::<u32>()
+                    .expect("CIRCUIT_FAILURE_THRESHOLD must be a number.")
+            })
+            .unwrap_or(5);
+        let circuit_cool_down_ms = std::env::var("CIRCUIT_COOL_DOWN_MS")
+            .map(|delay| delay.parse::<u64>().expect("CIRCUIT_COOL_DOWN_MS must be a number."))
+            .unwrap_or(30_000);
+        let spend_budgets = std::env::var("SPEND_BUDGETS")
+            .map(|budgets| {
+                serde_json::from_str(&budgets)
+                    .expect("SPEND_BUDGETS must be a JSON map of project or `key:{id}` to budget.")
+            })
+            .unwrap_or_default();
+        let response_cache_ttl_seconds = std::env::var("RESPONSE_CACHE_TTL_SECONDS")
+            .map(|ttl| {
+                ttl.parse::<u64>()
+                    .expect("RESPONSE_CACHE_TTL_SECONDS must be a number.")
+            })
+            .unwrap_or(3600);
+        let semantic_cache_max_distance = std::env::var("SEMANTIC_CACHE_MAX_DISTANCE")
+            .ok()
+            .map(|distance| {
+                distance
+                    .parse::<f64>()
+                    .expect("SEMANTIC_CACHE_MAX_DISTANCE must be a number.")
+            });
+        let admin_api_key = std::env::var("ADMIN_API_KEY")
+            .ok()
+            .filter(|key| !key.is_empty());
+        let rate_limits = std::env::var("RATE_LIMITS")
+            .map(|limits| {
+                serde_json::from_str(&limits)
+                    .expect("RATE_LIMITS must be a JSON object of ip, key and route limits.")
+            })
+            .unwrap_or_default();
+        let structured_output_max_retries = std::env::var("STRUCTURED_OUTPUT_MAX_RETRIES")
+            .map(|retries| {
+                retries
+                    .parse::<u32>()
+                    .expect("STRUCTURED_OUTPUT_MAX_RETRIES must be a number.")
+            })
+            .unwrap_or(2);
+        let transcription_max_duration_secs = std::env::var("TRANSCRIPTION_MAX_DURATION_SECS")
+            .map(|secs| {
+                secs.parse::<u32>()
+                    .expect("TRANSCRIPTION_MAX_DURATION_SECS must be a number.")
+            })
+            .unwrap_or(600);
+        let public_base_url = std::env::var("PUBLIC_BASE_URL")
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|_| "http://localhost:3001".to_string());
+        let generated_images_ttl_seconds = std::env::var("GENERATED_IMAGES_TTL_SECONDS")
+            .map(|ttl| {
+                ttl.parse::<u64>()
+                    .expect("GENERATED_IMAGES_TTL_SECONDS must be a number.")
+            })
+            .unwrap_or(86400);
+        let gemini_safety_settings = std::env::var("GEMINI_SAFETY_SETTINGS")
+            .map(|settings| {
+                serde_json::from_str(&settings)
+                    .expect("GEMINI_SAFETY_SETTINGS must be a JSON list of category and threshold.")
+            })
+            .unwrap_or_default();
         let rds_hostname = std::env::var("RDS_HOSTNAME").expect("RDS_HOSTNAME must be set.");
         let rds_port = std::env::var("RDS_PORT")
             .expect("RDS_PORT must be set.")
@@ -37,6 +168,26 @@ impl Secrets {
             google_vision_api_key,
             cloudflare_account,
             google_ai_studio_api_key,
+            anthropic_api_key,
+            anthropic_base_url,
+            open_ai_compatible,
+            fallback_chains,
+            retry_max_attempts,
+            retry_base_delay_ms,
+            retry_max_delay_ms,
+            provider_timeouts,
+            circuit_failure_threshold,
+            circuit_cool_down_ms,
+            spend_budgets,
+            response_cache_ttl_seconds,
+            semantic_cache_max_distance,
+            admin_api_key,
+            rate_limits,
+            structured_output_max_retries,
+            gemini_safety_settings,
+            transcription_max_duration_secs,
+            public_base_url,
+            generated_images_ttl_seconds,
             rds_hostname,
             rds_port,
             rds_db_name,
@@ -44,4 +195,75 @@ impl Secrets {
             rds_password,
         }
     }
+
+    // Timeouts of a provider, unset values come from the "default" entry
+    pub fn timeouts(&self, provider: &str) -> TimeoutSecrets {
+        let default = self.provider_timeouts.get("default").copied().unwrap_or_default();
+        let timeouts = self.provider_timeouts.get(provider).copied().unwrap_or_default();
+        TimeoutSecrets {
+            connect_ms: timeouts.connect_ms.or(default.connect_ms),
+            read_ms: timeouts.read_ms.or(default.read_ms),
+            total_ms: timeouts.total_ms.or(default.total_ms),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub struct TimeoutSecrets {
+    pub connect_ms: Option<u64>,
+    pub read_ms: Option<u64>,
+    pub total_ms: Option<u64>,
+}
+
+// USD limits, unset periods are unlimited
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct BudgetSecrets {
+    pub daily_usd: Option<f64>,
+    pub monthly_usd: Option<f64>,
+}
+
+// Inbound limits, unset ones are unlimited. Route limits apply per key, on top of the key limit
+#[derive(Deserialize, Debug, Clone)]
+pub struct RateLimitSecrets {
+    pub ip: Option<RateLimit>,
+    pub key: Option<RateLimit>,
+    #[serde(default)]
+    pub routes: HashMap<String, RateLimit>,
+}
+
+// Used when RATE_LIMITS is not set, the POI route fans out to several paid calls
+impl Default for RateLimitSecrets {
+    fn default() -> Self {
+        Self {
+            ip: Some(RateLimit {
+                requests_per_minute: 300,
+                burst: 100,
+            }),
+            key: Some(RateLimit {
+                requests_per_minute: 120,
+                burst: 30,
+            }),
+            routes: HashMap::from([(
+                "/api/v1/poi/from_image".to_string(),
+                RateLimit {
+                    requests_per_minute: 10,
+                    burst: 3,
+                },
+            )]),
+        }
+    }
+}
+
+// Self-hosted backends speaking the OpenAI chat API, e.g. Ollama, vLLM or LM Studio
+#[derive(Deserialize, Debug, Clone)]
+pub struct OpenAiCompatibleSecrets {
+    pub name: String,
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub models: Vec<String>,
+    pub vision_model: Option<String>,
+    pub max_tokens: Option<u32>,
+    // USD per million tokens, free when unset
+    pub prompt_price: Option<f64>,
+    pub completion_price: Option<f64>,
 }
@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::Row;
+
+use crate::{
+    api::llm_provider::TokenUsage, db::database_pool::DatabasePool,
+    models::custom_error::CustomError,
+};
+
+#[derive(Debug)]
+pub struct UsageRecord {
+    pub endpoint: String,
+    pub project: String,
+    pub api_key_id: Option<i32>,
+    pub provider: String,
+    pub model: String,
+    pub usage: Option<TokenUsage>,
+    pub cost_usd: Option<f64>,
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct UsageAggregateEntity {
+    pub day: NaiveDate,
+    pub project: String,
+    pub api_key_id: Option<i32>,
+    pub endpoint: String,
+    pub provider: String,
+    pub model: String,
+    pub requests: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+    pub cost_usd: f64,
+}
+
+pub struct UsageRepository {
+    db: Arc<DatabasePool>,
+}
+
+impl UsageRepository {
+    pub fn new(db: Arc<DatabasePool>) -> Self {
+        Self { db }
+    }
+
+    pub async fn insert_one(&self, record: &UsageRecord) -> Result<i32, CustomError> {
+        let id = sqlx::query_scalar(
+            "INSERT INTO token_usage \
+                (endpoint, project, api_key_id, provider, model, prompt_tokens, completion_tokens, total_tokens, cost_usd) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id",
+        )
+        .bind(&record.endpoint)
+        .bind(&record.project)
+        .bind(record.api_key_id)
+        .bind(&record.provider)
+        .bind(&record.model)
+        .bind(record.usage.map(|usage| usage.prompt_tokens as i32))
+        .bind(record.usage.map(|usage| usage.completion_tokens as i32))
+        .bind(record.usage.map(|usage| usage.total_tokens as i32))
+        .bind(record.cost_usd)
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok(id)
+    }
+
+    // Spend of a project since the start of the day and since the start of the month
+    pub async fn spend(
+        &self,
+        project: &str,
+        day_start: DateTime<Utc>,
+        month_start: DateTime<Utc>,
+    ) -> Result<(f64, f64), CustomError> {
+        let row = sqlx::query(&Self::spend_query("project"))
+            .bind(project)
+            .bind(day_start)
+            .bind(month_start)
+            .fetch_one(self.db.pool())
+            .await?;
+
+        Ok((row.try_get("daily")?, row.try_get("monthly")?))
+    }
+
+    // Same as `spend`, for the calls made with one API key
+    pub async fn key_spend(
+        &self,
+        api_key_id: i32,
+        day_start: DateTime<Utc>,
+        month_start: DateTime<Utc>,
+    ) -> Result<(f64, f64), CustomError> {
+        let row = sqlx::query(&Self::spend_query("api_key_id"))
+            .bind(api_key_id)
+            .bind(day_start)
+            .bind(month_start)
+            .fetch_one(self.db.pool())
+            .await?;
+
+        Ok((row.try_get("daily")?, row.try_get("monthly")?))
+    }
+
+    fn spend_query(column: &str) -> String {
+        format!(
+            "SELECT \
+                COALESCE(SUM(cost_usd) FILTER (WHERE created_at >= $2), 0) AS daily, \
+                COALESCE(SUM(cost_usd), 0) AS monthly \
+             FROM token_usage WHERE {} = $1 AND created_at >= $3",
+            column
+        )
+    }
+
+    // Daily totals per project, API key, endpoint and model, both bounds inclusive (UTC days)
+    pub async fn aggregate_daily(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<UsageAggregateEntity>, CustomError> {
+        let entities = sqlx::query_as::<_, UsageAggregateEntity>(
+            "SELECT (created_at AT TIME ZONE 'UTC')::date AS day, project, api_key_id, endpoint, provider, model, \
+                COUNT(*) AS requests, \
+                COALESCE(SUM(prompt_tokens), 0)::BIGINT AS prompt_tokens, \
+                COALESCE(SUM(completion_tokens), 0)::BIGINT AS completion_tokens, \
+                COALESCE(SUM(total_tokens), 0)::BIGINT AS total_tokens, \
+                COALESCE(SUM(cost_usd), 0) AS cost_usd \
+             FROM token_usage \
+             WHERE ($1::date IS NULL OR (created_at AT TIME ZONE 'UTC')::date >= $1) \
+               AND ($2::date IS NULL OR (created_at AT TIME ZONE 'UTC')::date <= $2) \
+             GROUP BY day, project, api_key_id, endpoint, provider, model \
+             ORDER BY day DESC, project, api_key_id, endpoint, provider, model",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(entities)
+    }
+}
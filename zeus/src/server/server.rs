@@ -24,6 +24,7 @@ impl ZeusServer {
                     header::CONTENT_TYPE,
                     header::AUTHORIZATION,
                     header::ACCEPT,
+                    header::HeaderName::from_static(handlers::auth_middleware::API_KEY_HEADER),
                 ])
                 .supports_credentials();
             App::new()
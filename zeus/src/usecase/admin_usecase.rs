@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use crate::{
+    api::circuit_breaker::{CircuitBreaker, CircuitState},
+    models::{custom_error::CustomError, usage_query::UsageQuery},
+    repository::usage_repository::UsageAggregateEntity,
+    usecase::{
+        cache_usecase::{CacheStats, CacheUsecase},
+        usage_usecase::UsageUsecase,
+    },
+};
+
+pub struct AdminUsecase {
+    circuits: Vec<Arc<CircuitBreaker>>,
+    usage: Arc<UsageUsecase>,
+    cache: Arc<CacheUsecase>,
+}
+
+impl AdminUsecase {
+    pub fn new(
+        circuits: Vec<Arc<CircuitBreaker>>,
+        usage: Arc<UsageUsecase>,
+        cache: Arc<CacheUsecase>,
+    ) -> Self {
+        Self {
+            circuits,
+            usage,
+            cache,
+        }
+    }
+
+    pub fn cache(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    pub async fn usage(&self, query: &UsageQuery) -> Result<Vec<UsageAggregateEntity>, CustomError> {
+        self.usage.daily(query).await
+    }
+
+    pub fn circuits(&self) -> Vec<CircuitState> {
+        let mut circuits: Vec<CircuitState> =
+            self.circuits.iter().map(|circuit| circuit.state()).collect();
+        circuits.sort_by(|a, b| a.provider.cmp(&b.provider));
+        circuits
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    models::{
+        api_key::{ApiKeyIdentity, CreateApiKeyRequest, CreatedApiKey},
+        call_context::CallContext,
+        custom_error::CustomError,
+    },
+    repository::{
+        api_key_repository::{ApiKeyEntity, ApiKeyRepository},
+        secrets::Secrets,
+    },
+};
+
+// Issues gateway keys and authenticates callers, only sha256 hashes of the keys are stored
+pub struct ApiKeyUsecase {
+    keys: Arc<ApiKeyRepository>,
+    admin_key_hash: Option<String>,
+}
+
+impl ApiKeyUsecase {
+    const KEY_PREFIX: &'static str = "zk-";
+    const KEY_BYTES: usize = 24;
+    // Stored in clear to tell keys apart in listings
+    const VISIBLE_CHARS: usize = 8;
+
+    pub fn new(keys: Arc<ApiKeyRepository>, secrets: &Secrets) -> Self {
+        if secrets.admin_api_key.is_none() {
+            log::warn!("ADMIN_API_KEY is not set, admin endpoints are locked");
+        }
+        Self {
+            keys,
+            admin_key_hash: secrets.admin_api_key.as_deref().map(Self::hash),
+        }
+    }
+
+    pub async fn create(&self, request: &CreateApiKeyRequest) -> Result<CreatedApiKey, CustomError> {
+        if request.name.trim().is_empty() {
+            return Err(CustomError::InvalidRequest("name must not be empty".to_string()));
+        }
+        let metadata = request.metadata.clone().unwrap_or_else(|| serde_json::json!({}));
+        if !metadata.is_object() {
+            return Err(CustomError::InvalidRequest("metadata must be an object".to_string()));
+        }
+        let project = request
+            .project
+            .as_deref()
+            .filter(|project| !project.is_empty())
+            .unwrap_or(CallContext::DEFAULT_PROJECT);
+
+        let mut secret = [0u8; Self::KEY_BYTES];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let key = format!("{}{}", Self::KEY_PREFIX, hex::encode(secret));
+        let key_prefix = &key[..Self::KEY_PREFIX.len() + Self::VISIBLE_CHARS];
+
+        let entity = self
+            .keys
+            .insert_one(
+                request.name.trim(),
+                key_prefix,
+                &Self::hash(&key),
+                project,
+                metadata,
+                request.rate_limit,
+            )
+            .await?;
+        log::info!("Issued API key {} ({}) for project {}", entity.id, entity.name, entity.project);
+        Ok(CreatedApiKey { key, entity })
+    }
+
+    pub async fn list(&self) -> Result<Vec<ApiKeyEntity>, CustomError> {
+        self.keys.fetch_many().await
+    }
+
+    pub async fn revoke(&self, id: i32) -> Result<ApiKeyEntity, CustomError> {
+        let entity = self
+            .keys
+            .revoke(id)
+            .await?
+            .ok_or_else(|| CustomError::NotFound(format!("API key {}", id)))?;
+        log::info!("Revoked API key {} ({})", entity.id, entity.name);
+        Ok(entity)
+    }
+
+    pub async fn authenticate(&self, key: Option<&str>) -> Result<ApiKeyIdentity, CustomError> {
+        let key = key.ok_or_else(|| CustomError::Unauthorized("missing API key".to_string()))?;
+        let entity = self
+            .keys
+            .touch_active_by_hash(&Self::hash(key))
+            .await?
+            .ok_or_else(|| CustomError::Unauthorized("invalid or revoked API key".to_string()))?;
+        Ok(ApiKeyIdentity {
+            id: entity.id,
+            name: entity.name,
+            project: entity.project,
+            rate_limit: entity.rate_limit.map(|rate_limit| rate_limit.0),
+        })
+    }
+
+    pub fn authenticate_admin(&self, key: Option<&str>) -> Result<(), CustomError> {
+        match (key, &self.admin_key_hash) {
+            (Some(key), Some(admin_key_hash)) if Self::hash(key) == *admin_key_hash => Ok(()),
+            _ => Err(CustomError::Unauthorized("invalid admin key".to_string())),
+        }
+    }
+
+    fn hash(key: &str) -> String {
+        hex::encode(Sha256::digest(key.as_bytes()))
+    }
+}
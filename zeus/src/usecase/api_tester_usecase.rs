@@ -5,40 +5,57 @@ use reqwest::StatusCode;
 
 use crate::{
     api::{
-        cloudflare_ai::{CloudflareApi, CloudflareModel},
-        google_gemini::GeminiApi,
+        cloudflare_ai::CloudflareApi,
         google_places::{GoogleGeocodeApiRequest, GoogleGeocodeApiResponse, GooglePlacesApi},
         google_vision::{GoogleVisionApi, GoogleVisionApiResponse, VisionFeatures},
-        open_ai::{OpenAIApi, OpenAiModel},
+        llm_provider::{ChatRequest, ChatResponse, LlmProvider, MeteredStream},
+        open_ai::OpenAIApi,
+        provider_registry::{ProviderInfo, ProviderRegistry},
+    },
+    models::{
+        call_context::CallContext,
+        chat_message::{ChatMessage, ChatRole},
+        completion_model::CompletionRequest,
+        custom_error::CustomError,
+        embedding_body_request::EmbeddintBodyRequest,
+        sampling_params::SamplingParams,
+        translation::{TranslationRequest, TranslationResponse},
     },
-    models::{custom_error::CustomError, self, embedding_body_request::EmbeddintBodyRequest},
     repository::{local_storage::LocalStorage, prompt_provider},
+    usecase::{
+        cache_usecase::{CacheLookup, CacheUsecase},
+        structured_output_usecase::StructuredOutputUsecase,
+        usage_usecase::UsageUsecase,
+    },
     utils::{gps_utils::GpsUtils, image_utils::ImageUtils},
 };
 
 pub struct ExtApiUsecase {
-    open_ai: Arc<OpenAIApi>,
+    providers: Arc<ProviderRegistry>,
+    usage: Arc<UsageUsecase>,
+    cache: Arc<CacheUsecase>,
+    structured: Arc<StructuredOutputUsecase>,
     google_vision_api: Arc<GoogleVisionApi>,
-    cloudflare_api: Arc<CloudflareApi>,
-    gemini_api: Arc<GeminiApi>,
     google_places: Arc<GooglePlacesApi>,
     local_storage: Arc<LocalStorage>,
 }
 
 impl ExtApiUsecase {
     pub fn new(
-        open_ai: Arc<OpenAIApi>,
+        providers: Arc<ProviderRegistry>,
+        usage: Arc<UsageUsecase>,
+        cache: Arc<CacheUsecase>,
+        structured: Arc<StructuredOutputUsecase>,
         google_vision_api: Arc<GoogleVisionApi>,
-        cloudflare_api: Arc<CloudflareApi>,
-        gemini_api: Arc<GeminiApi>,
         google_places: Arc<GooglePlacesApi>,
         local_storage: Arc<LocalStorage>,
     ) -> Self {
         Self {
-            open_ai,
+            providers,
+            usage,
+            cache,
+            structured,
             google_vision_api,
-            cloudflare_api,
-            gemini_api,
             google_places,
             local_storage,
         }
@@ -70,70 +87,189 @@ impl ExtApiUsecase {
         self.google_places.geocoding(location).await
     }
 
-    // OpenAI API
+    // LLM providers
+
+    pub fn providers(&self) -> Vec<ProviderInfo> {
+        self.providers.providers()
+    }
 
-    pub async fn gpt_completion(&self, message: &str) -> Result<String, CustomError> {
-        self.open_ai
-            .completion(
-                OpenAiModel::Gpt4Turbo,
-                prompt_provider::Prompt::Poi,
-                message,
+    pub async fn completion(
+        &self,
+        context: &CallContext,
+        provider: &str,
+        request: &CompletionRequest,
+    ) -> Result<ChatResponse, CustomError> {
+        let provider = self.providers.provider(provider)?;
+        let request = Self::chat_request(provider.as_ref(), request)?;
+        let provider_name = provider.name().to_string();
+        let miss = match self.cache.get(context, &provider_name, &request).await {
+            CacheLookup::Hit(response) => return Ok(response),
+            CacheLookup::Miss(miss) => miss,
+        };
+        self.usage.check_budget(context).await?;
+        let response = self.structured.chat(context, provider, &request).await?;
+        self.cache.put(miss, &response).await;
+        self.usage
+            .record(
+                context,
+                &response.provider,
+                &response.model,
+                response.usage,
+                response.cost_usd,
             )
-            .await
+            .await;
+        Ok(response)
     }
 
-    pub async fn gpt_visual(&self, f: TempFile) -> Result<String, CustomError> {
-        let path = self.local_storage.persist(f)?;
-        match ImageUtils::to_base64(&path) {
-            Ok(base64_image) => {
-                self.open_ai
-                    .visual(
-                        OpenAiModel::Gpt4Visual,
-                        prompt_provider::Prompt::Poi,
-                        &base64_image,
-                    )
-                    .await
-            }
-            Err(e) => Err(e),
-        }
+    pub async fn completion_stream(
+        &self,
+        context: &CallContext,
+        provider: &str,
+        request: &CompletionRequest,
+    ) -> Result<MeteredStream, CustomError> {
+        let provider = self.providers.provider(provider)?;
+        let request = Self::chat_request(provider.as_ref(), request)?;
+        self.usage.check_budget(context).await?;
+        let routed = self.providers.chat_stream(provider, &request).await?;
+        Ok(Arc::clone(&self.usage).meter(context, &request, routed))
     }
 
-    // Gemini API | Google
+    // The POI prompt stays the system prompt unless the caller brings its own
+    fn chat_request(
+        provider: &dyn LlmProvider,
+        request: &CompletionRequest,
+    ) -> Result<ChatRequest, CustomError> {
+        let model = request
+            .model
+            .as_deref()
+            .unwrap_or(provider.default_model());
+        provider.model(model)?.validate(&request.params)?;
 
-    pub async fn gemini_completion(&self, message: &str) -> Result<String, CustomError> {
-        self.gemini_api
-            .completion(prompt_provider::Prompt::Poi, message)
-            .await
+        let mut messages = request.history()?;
+        if !messages
+            .iter()
+            .any(|message| message.role == ChatRole::System)
+        {
+            messages.insert(
+                0,
+                ChatMessage::new(ChatRole::System, prompt_provider::Prompt::Poi.prompt()),
+            );
+        }
+        Ok(ChatRequest {
+            model: model.to_string(),
+            messages,
+            params: request.params.clone(),
+            tools: Vec::new(),
+            tool_choice: None,
+            response_format: request.response_format.clone(),
+            safety_settings: request.safety_settings.clone(),
+        })
     }
 
-    pub async fn gemini_visual(&self, f: TempFile) -> Result<String, CustomError> {
+    pub async fn visual(
+        &self,
+        context: &CallContext,
+        provider: &str,
+        f: TempFile,
+    ) -> Result<String, CustomError> {
+        let provider = self.providers.provider(provider)?;
         let path = self.local_storage.persist(f)?;
-        match ImageUtils::to_base64(&path) {
-            Ok(base64_image) => {
-                self.gemini_api
-                    .visual(prompt_provider::Prompt::Poi, &base64_image)
-                    .await
-            }
-            Err(e) => Err(e),
-        }
+        let base64_image = ImageUtils::to_base64(&path)?;
+        self.usage.check_budget(context).await?;
+        let response = provider
+            .visual(prompt_provider::Prompt::Poi, &base64_image)
+            .await?;
+        // Priced as the requested model, OpenAI answers with the name of a snapshot
+        let model = provider.vision_model().unwrap_or_default();
+        self.usage
+            .record(
+                context,
+                provider.name(),
+                model,
+                response.usage,
+                provider.cost(model, response.usage),
+            )
+            .await;
+        Ok(response.content)
     }
 
-    // Cloudflare API
-
-    pub async fn llama_completion(&self, message: &str) -> Result<String, CustomError> {
-        self.cloudflare_api
-            .completion(
-                CloudflareModel::Llama27b,
-                prompt_provider::Prompt::Poi,
-                message,
+    // Embeddings come without usage, only Workers AI serves them and it is free
+    pub async fn embedding(
+        &self,
+        context: &CallContext,
+        provider: &str,
+        body: &EmbeddintBodyRequest,
+    ) -> Result<Vec<Vec<f64>>, CustomError> {
+        let provider = self.providers.provider(provider)?;
+        self.usage.check_budget(context).await?;
+        let embeddings = provider.embedding(body).await?;
+        self.usage
+            .record(
+                context,
+                provider.name(),
+                provider.embedding_model().unwrap_or_default(),
+                None,
+                (provider.name() == CloudflareApi::NAME).then_some(0.0),
             )
-            .await
+            .await;
+        Ok(embeddings)
     }
 
-    pub async fn embedding(&self, body: &EmbeddintBodyRequest) -> Result<Vec<Vec<f64>>, CustomError> {
-        self.cloudflare_api
-            .embedding(body)
+    // m2m100 first, an LLM for the pairs it doesn't know or while it is unavailable
+    pub async fn translation(
+        &self,
+        context: &CallContext,
+        request: &TranslationRequest,
+    ) -> Result<TranslationResponse, CustomError> {
+        request.validate()?;
+        match self
+            .providers
+            .provider(CloudflareApi::NAME)?
+            .translation(request)
             .await
-    }
+        {
+            Err(e) if matches!(e, CustomError::UnsupportedCapability(_)) || e.is_transient() => {
+                log::warn!("Translating with an LLM: {}", e)
+            }
+            result => return result,
+        }
 
+        let provider = self
+            .providers
+            .provider(request.fallback_provider.as_deref().unwrap_or(OpenAIApi::NAME))?;
+        let prompt = prompt_provider::Prompt::Translation
+            .prompt()
+            .replace("{source}", &request.source_lang)
+            .replace("{target}", &request.target_lang);
+        let chat_request = ChatRequest {
+            model: provider.default_model().to_string(),
+            messages: vec![
+                ChatMessage::new(ChatRole::System, prompt),
+                ChatMessage::new(ChatRole::User, request.text.clone()),
+            ],
+            params: SamplingParams::default(),
+            tools: Vec::new(),
+            tool_choice: None,
+            response_format: None,
+            safety_settings: Vec::new(),
+        };
+        self.usage.check_budget(context).await?;
+        let response = self.providers.chat(provider, &chat_request).await?;
+        self.usage
+            .record(
+                context,
+                &response.provider,
+                &response.model,
+                response.usage,
+                response.cost_usd,
+            )
+            .await;
+        Ok(TranslationResponse {
+            provider: response.provider,
+            model: response.model,
+            source_lang: request.source_lang.clone(),
+            target_lang: request.target_lang.clone(),
+            translated_text: response.content.trim().to_string(),
+        })
+    }
 }
@@ -0,0 +1,356 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    api::{
+        cloudflare_ai::CloudflareApi,
+        llm_provider::{ChatRequest, ChatResponse},
+        provider_registry::ProviderRegistry,
+    },
+    models::{
+        call_context::{CacheDirective, CallContext},
+        chat_message::{ChatMessage, ChatRole},
+        custom_error::CustomError,
+        embedding_body_request::EmbeddintBodyRequest,
+    },
+    repository::{
+        key_value_repository::KeyValueRepository,
+        key_value_vector_repository::KeyValueVectorRepository, secrets::Secrets,
+    },
+    usecase::usage_usecase::UsageUsecase,
+};
+
+// Chat completion cache: exact matches keyed by a hash of the normalized request, then
+// near-duplicate prompts found by embedding distance when the semantic cache is enabled.
+// Entries are never shared between projects
+pub struct CacheUsecase {
+    store: Arc<KeyValueRepository>,
+    vectors: Arc<KeyValueVectorRepository>,
+    providers: Arc<ProviderRegistry>,
+    usage: Arc<UsageUsecase>,
+    ttl: Option<chrono::Duration>,
+    max_distance: Option<f64>,
+    hits: AtomicU64,
+    semantic_hits: AtomicU64,
+    misses: AtomicU64,
+    bypasses: AtomicU64,
+}
+
+pub enum CacheLookup {
+    Hit(ChatResponse),
+    Miss(CacheMiss),
+}
+
+// What `put` needs to store the answer of a missed lookup
+pub struct CacheMiss {
+    store: bool,
+    key: String,
+    semantic: Option<SemanticKey>,
+}
+
+struct SemanticKey {
+    scope: String,
+    prompt: String,
+    embedding: Vec<f32>,
+}
+
+impl CacheUsecase {
+    pub fn new(
+        store: Arc<KeyValueRepository>,
+        vectors: Arc<KeyValueVectorRepository>,
+        providers: Arc<ProviderRegistry>,
+        usage: Arc<UsageUsecase>,
+        secrets: &Secrets,
+    ) -> Self {
+        Self {
+            store,
+            vectors,
+            providers,
+            usage,
+            // A zero TTL disables the cache
+            ttl: (secrets.response_cache_ttl_seconds > 0)
+                .then(|| chrono::Duration::seconds(secrets.response_cache_ttl_seconds as i64)),
+            max_distance: secrets.semantic_cache_max_distance,
+            hits: AtomicU64::new(0),
+            semantic_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            bypasses: AtomicU64::new(0),
+        }
+    }
+
+    // Cache failures are logged and treated as misses
+    pub async fn get(
+        &self,
+        context: &CallContext,
+        provider: &str,
+        request: &ChatRequest,
+    ) -> CacheLookup {
+        let mut miss = CacheMiss {
+            store: self.ttl.is_some() && context.cache != CacheDirective::NoStore,
+            key: Self::key(&context.project, provider, request),
+            semantic: None,
+        };
+        if self.ttl.is_none() {
+            return CacheLookup::Miss(miss);
+        }
+        let lookup = context.cache == CacheDirective::Default;
+        if !lookup {
+            self.bypasses.fetch_add(1, Ordering::Relaxed);
+        }
+
+        if lookup {
+            if let Some(response) = self.exact(&miss.key).await {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return CacheLookup::Hit(Self::cached(response));
+            }
+        }
+        if self.max_distance.is_some() && (lookup || miss.store) {
+            miss.semantic = self.semantic_key(context, provider, request).await;
+        }
+        if let (true, Some(semantic)) = (lookup, &miss.semantic) {
+            if let Some(response) = self.nearest(semantic).await {
+                self.semantic_hits.fetch_add(1, Ordering::Relaxed);
+                return CacheLookup::Hit(Self::cached(response));
+            }
+        }
+
+        if lookup {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        CacheLookup::Miss(miss)
+    }
+
+    pub async fn put(&self, miss: CacheMiss, response: &ChatResponse) {
+        let (Some(ttl), true) = (self.ttl, miss.store) else {
+            return;
+        };
+        let expires_at = chrono::Utc::now() + ttl;
+        let json_body = match serde_json::to_value(response) {
+            Ok(json_body) => json_body,
+            Err(e) => {
+                log::error!("Failed to serialize cache entry: {}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = self
+            .store
+            .upsert_by_key(&miss.key, json_body.clone(), Some(expires_at))
+            .await
+        {
+            log::error!("Failed to store cache entry: {}", e);
+        }
+
+        if let Some(semantic) = miss.semantic {
+            let metadata = json!({
+                "scope": semantic.scope,
+                "prompt": semantic.prompt,
+                "response": json_body,
+                "expires_at": expires_at,
+            });
+            if let Err(e) = self.vectors.insert_one(semantic.embedding, metadata).await {
+                log::error!("Failed to store semantic cache entry: {}", e);
+            }
+        }
+    }
+
+    // Run periodically rather than on every store
+    pub async fn evict_expired(&self) {
+        match self.store.delete_expired().await {
+            Ok(0) => {}
+            Ok(evicted) => log::info!("Removed {} expired cache entries", evicted),
+            Err(e) => log::error!("Failed to evict expired cache entries: {}", e),
+        }
+        if self.max_distance.is_none() {
+            return;
+        }
+        match self.vectors.delete_expired().await {
+            Ok(0) => {}
+            Ok(evicted) => log::info!("Removed {} expired semantic cache entries", evicted),
+            Err(e) => log::error!("Failed to evict expired semantic cache entries: {}", e),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let semantic_hits = self.semantic_hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + semantic_hits + misses;
+        CacheStats {
+            enabled: self.ttl.is_some(),
+            semantic: self.ttl.is_some() && self.max_distance.is_some(),
+            hits,
+            semantic_hits,
+            misses,
+            bypasses: self.bypasses.load(Ordering::Relaxed),
+            hit_rate: if lookups == 0 {
+                0.0
+            } else {
+                (hits + semantic_hits) as f64 / lookups as f64
+            },
+        }
+    }
+
+    async fn exact(&self, key: &str) -> Option<ChatResponse> {
+        match self.store.fetch_by_key(key).await {
+            Ok(cached) => cached.and_then(Self::response),
+            Err(e) => {
+                log::error!("Cache lookup failed: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn nearest(&self, semantic: &SemanticKey) -> Option<ChatResponse> {
+        let max_distance = self.max_distance?;
+        match self
+            .vectors
+            .search_by_distance(semantic.embedding.clone(), &semantic.scope, max_distance)
+            .await
+        {
+            Ok(Some((mut metadata, distance))) => {
+                log::debug!(
+                    "Semantic cache hit at distance {:.4} for \"{}\" ~ {}",
+                    distance,
+                    semantic.prompt,
+                    metadata["prompt"]
+                );
+                Self::response(metadata["response"].take())
+            }
+            Ok(None) => None,
+            Err(e) => {
+                log::error!("Semantic cache lookup failed: {}", e);
+                None
+            }
+        }
+    }
+
+    // The last user turn is embedded, everything else has to match exactly through the scope
+    async fn semantic_key(
+        &self,
+        context: &CallContext,
+        provider: &str,
+        request: &ChatRequest,
+    ) -> Option<SemanticKey> {
+        let (last, history) = request.messages.split_last()?;
+        if last.role != ChatRole::User {
+            return None;
+        }
+        let prompt = last.content.trim().to_string();
+        let embedding = match self.embed(context, &prompt).await {
+            Ok(embedding) => embedding,
+            Err(e) => {
+                log::error!("Failed to embed prompt for the semantic cache: {}", e);
+                return None;
+            }
+        };
+        let scope = Self::hash(
+            "semantic",
+            &Self::normalized(&context.project, provider, request, history),
+        );
+        Some(SemanticKey {
+            scope,
+            prompt,
+            embedding,
+        })
+    }
+
+    // Workers AI embeddings are free, recorded at no cost
+    async fn embed(&self, context: &CallContext, prompt: &str) -> Result<Vec<f32>, CustomError> {
+        let provider = self.providers.provider(CloudflareApi::NAME)?;
+        let embeddings = provider
+            .embedding(&EmbeddintBodyRequest::new(vec![prompt.to_string()]))
+            .await?;
+        self.usage
+            .record(
+                context,
+                provider.name(),
+                provider.embedding_model().unwrap_or_default(),
+                None,
+                Some(0.0),
+            )
+            .await;
+        embeddings
+            .into_iter()
+            .next()
+            .map(|embedding| embedding.into_iter().map(|value| value as f32).collect())
+            .ok_or(CustomError::NoContentFromAssistant)
+    }
+
+    fn cached(response: ChatResponse) -> ChatResponse {
+        ChatResponse {
+            cached: true,
+            cost_usd: Some(0.0),
+            ..response
+        }
+    }
+
+    fn response(json_body: serde_json::Value) -> Option<ChatResponse> {
+        serde_json::from_value(json_body)
+            .map_err(|e| log::error!("Malformed cache entry: {}", e))
+            .ok()
+    }
+
+    fn key(project: &str, provider: &str, request: &ChatRequest) -> String {
+        Self::hash(
+            "chat",
+            &Self::normalized(project, provider, request, &request.messages),
+        )
+    }
+
+    // Surrounding whitespace doesn't change the answer, so it doesn't change the key either
+    fn normalized(
+        project: &str,
+        provider: &str,
+        request: &ChatRequest,
+        messages: &[ChatMessage],
+    ) -> serde_json::Value {
+        let messages: Vec<_> = messages
+            .iter()
+            .map(|message| {
+                json!({
+                    "role": message.role,
+                    "content": message.content.trim(),
+                    "tool_calls": message.tool_calls,
+                    "tool_call_id": message.tool_call_id,
+                })
+            })
+            .collect();
+        json!({
+            "project": project,
+            "provider": provider,
+            "model": request.model,
+            "messages": messages,
+            "params": request.params,
+            "tools": request.tools,
+            "tool_choice": request.tool_choice,
+            "response_format": request.response_format,
+            "safety_settings": request.safety_settings,
+        })
+    }
+
+    fn hash(prefix: &str, normalized: &serde_json::Value) -> String {
+        format!(
+            "{}:{}",
+            prefix,
+            hex::encode(Sha256::digest(normalized.to_string().as_bytes()))
+        )
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct CacheStats {
+    pub enabled: bool,
+    pub semantic: bool,
+    pub hits: u64,
+    pub semantic_hits: u64,
+    pub misses: u64,
+    pub bypasses: u64,
+    pub hit_rate: f64,
+}
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use futures_util::{
+    future,
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
+
+use crate::{
+    api::{
+        llm_provider::MeteredStream,
+        provider_registry::ProviderRegistry,
+    },
+    models::{
+        call_context::CallContext,
+        chat_completion_request::{
+            ChatCompletionChunk, ChatCompletionRequest, ChatCompletionResponse,
+        },
+        custom_error::CustomError,
+    },
+    usecase::{
+        cache_usecase::{CacheLookup, CacheUsecase},
+        structured_output_usecase::StructuredOutputUsecase,
+        usage_usecase::UsageUsecase,
+    },
+};
+
+pub struct ChatUsecase {
+    providers: Arc<ProviderRegistry>,
+    usage: Arc<UsageUsecase>,
+    cache: Arc<CacheUsecase>,
+    structured: Arc<StructuredOutputUsecase>,
+}
+
+impl ChatUsecase {
+    pub fn new(
+        providers: Arc<ProviderRegistry>,
+        usage: Arc<UsageUsecase>,
+        cache: Arc<CacheUsecase>,
+        structured: Arc<StructuredOutputUsecase>,
+    ) -> Self {
+        Self {
+            providers,
+            usage,
+            cache,
+            structured,
+        }
+    }
+
+    pub async fn chat_completion(
+        &self,
+        context: &CallContext,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, CustomError> {
+        let provider = self.providers.provider_for_model(&request.model)?;
+        provider.model(&request.model)?.validate(&request.params)?;
+        let chat_request = request.chat_request();
+        let provider_name = provider.name().to_string();
+        let miss = match self.cache.get(context, &provider_name, &chat_request).await {
+            CacheLookup::Hit(response) => return Ok(ChatCompletionResponse::new(response)),
+            CacheLookup::Miss(miss) => miss,
+        };
+        self.usage.check_budget(context).await?;
+        log::debug!("Routing {} to {}", request.model, provider_name);
+        let response = self.structured.chat(context, provider, &chat_request).await?;
+        self.cache.put(miss, &response).await;
+        self.usage
+            .record(
+                context,
+                &response.provider,
+                &response.model,
+                response.usage,
+                response.cost_usd,
+            )
+            .await;
+        Ok(ChatCompletionResponse::new(response))
+    }
+
+    pub async fn chat_completion_stream(
+        &self,
+        context: &CallContext,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionStream, CustomError> {
+        let provider = self.providers.provider_for_model(&request.model)?;
+        provider.model(&request.model)?.validate(&request.params)?;
+        self.usage.check_budget(context).await?;
+        log::debug!("Streaming {} from {}", request.model, provider.name());
+        let chat_request = request.chat_request();
+        let routed = self.providers.chat_stream(provider, &chat_request).await?;
+        let MeteredStream {
+            provider,
+            model,
+            stream: deltas,
+        } = Arc::clone(&self.usage).meter(context, &chat_request, routed);
+
+        let id = format!("chatcmpl-{}", ulid::Ulid::new());
+        let created = chrono::Utc::now().timestamp();
+        let last = ChatCompletionChunk::new(&id, created, &model, None, Some("stop".to_string()));
+        let chunk_model = model.clone();
+        let chunks = deltas
+            .map_ok(move |text| {
+                ChatCompletionChunk::new(&id, created, &chunk_model, Some(text), None)
+            })
+            .chain(stream::once(future::ready(Ok(last))))
+            .boxed();
+        Ok(ChatCompletionStream {
+            provider,
+            model,
+            chunks,
+        })
+    }
+}
+
+pub struct ChatCompletionStream {
+    pub provider: String,
+    pub model: String,
+    pub chunks: BoxStream<'static, Result<ChatCompletionChunk, CustomError>>,
+}
@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use actix_multipart::form::tempfile::TempFile;
+use base64::{engine::general_purpose, Engine};
+
+use crate::{
+    api::{
+        cloudflare_ai::{CloudflareApi, CloudflareModel, DetectedObject, ImageLabel},
+        open_ai::{OpenAIApi, OpenAiModel},
+    },
+    models::{
+        call_context::CallContext,
+        custom_error::CustomError,
+        image_generation::{
+            GeneratedImage, ImageGenerationRequest, ImageGenerationResponse, ImageResponseFormat,
+        },
+        speech_request::{AudioStream, SpeechRequest},
+        transcription::Transcription,
+    },
+    repository::{local_storage::LocalStorage, secrets::Secrets},
+    usecase::usage_usecase::UsageUsecase,
+    utils::audio_utils::AudioUtils,
+};
+
+pub struct MediaUsecase {
+    open_ai_api: Arc<OpenAIApi>,
+    cloudflare_ai: Arc<CloudflareApi>,
+    local_storage: Arc<LocalStorage>,
+    usage: Arc<UsageUsecase>,
+    max_audio_secs: u32,
+    images_url: String,
+}
+
+impl MediaUsecase {
+    // OpenAI rejects audio uploads above 25 MB
+    const MAX_AUDIO_BYTES: usize = 25 * 1024 * 1024;
+
+    pub fn new(
+        open_ai_api: Arc<OpenAIApi>,
+        cloudflare_ai: Arc<CloudflareApi>,
+        local_storage: Arc<LocalStorage>,
+        usage: Arc<UsageUsecase>,
+        secrets: &Secrets,
+    ) -> Self {
+        Self {
+            open_ai_api,
+            cloudflare_ai,
+            local_storage,
+            usage,
+            max_audio_secs: secrets.transcription_max_duration_secs,
+            images_url: format!("{}/api/v1/images", secrets.public_base_url),
+        }
+    }
+
+    // Cloudflare image models, free on Workers AI but still gated and recorded
+
+    pub async fn classification(
+        &self,
+        context: &CallContext,
+        f: TempFile,
+    ) -> Result<Vec<ImageLabel>, CustomError> {
+        let image = self.read(f)?;
+        self.usage.check_budget(context).await?;
+        let labels = self.cloudflare_ai.image_classification(image).await?;
+        self.record_cloudflare(context, CloudflareModel::Resnet50).await;
+        Ok(labels)
+    }
+
+    pub async fn detection(
+        &self,
+        context: &CallContext,
+        f: TempFile,
+    ) -> Result<Vec<DetectedObject>, CustomError> {
+        let image = self.read(f)?;
+        self.usage.check_budget(context).await?;
+        let objects = self.cloudflare_ai.object_detection(image).await?;
+        self.record_cloudflare(context, CloudflareModel::DetrResnet50).await;
+        Ok(objects)
+    }
+
+    async fn record_cloudflare(&self, context: &CallContext, model: CloudflareModel) {
+        self.usage
+            .record(context, CloudflareApi::NAME, model.name(), None, Some(0.0))
+            .await;
+    }
+
+    // Whisper speech to text, billed per minute of audio. Workers AI is free
+
+    pub async fn transcription(
+        &self,
+        context: &CallContext,
+        provider: &str,
+        f: TempFile,
+    ) -> Result<Transcription, CustomError> {
+        if provider != OpenAIApi::NAME && provider != CloudflareApi::NAME {
+            return Err(CustomError::UnknownProvider(provider.to_string()));
+        }
+        if f.size > Self::MAX_AUDIO_BYTES {
+            return Err(CustomError::InvalidRequest(format!(
+                "audio files are limited to {} MB",
+                Self::MAX_AUDIO_BYTES / 1024 / 1024
+            )));
+        }
+        let Some(extension) = f
+            .file_name
+            .as_deref()
+            .and_then(AudioUtils::extension)
+            .filter(|extension| AudioUtils::EXTENSIONS.contains(&extension.as_str()))
+        else {
+            return Err(CustomError::InvalidRequest(format!(
+                "audio must be one of {}",
+                AudioUtils::EXTENSIONS.join(", ")
+            )));
+        };
+
+        // Rejected uploads are dropped with their temporary file
+        let duration_secs = AudioUtils::duration_secs(f.file.path(), &extension)?;
+        if duration_secs > self.max_audio_secs as f64 {
+            return Err(CustomError::InvalidRequest(format!(
+                "audio is {:.0}s long, the limit is {}s",
+                duration_secs, self.max_audio_secs
+            )));
+        }
+        self.usage.check_budget(context).await?;
+        let path = self.local_storage.persist(f)?;
+        log::info!("Transcribing {:.1}s of audio with {}", duration_secs, provider);
+
+        let transcription = if provider == OpenAIApi::NAME {
+            self.open_ai_api.transcription(&path).await?
+        } else {
+            let audio = std::fs::read(&path).map_err(CustomError::IoError)?;
+            self.cloudflare_ai.transcription(audio).await?
+        };
+        let (model, cost_usd) = if provider == OpenAIApi::NAME {
+            (
+                OpenAiModel::Whisper.name(),
+                duration_secs / 60.0 * OpenAIApi::WHISPER_USD_PER_MINUTE,
+            )
+        } else {
+            (CloudflareModel::Whisper.name(), 0.0)
+        };
+        self.usage
+            .record(context, provider, model, None, Some(cost_usd))
+            .await;
+        Ok(transcription)
+    }
+
+    // OpenAI text to speech, billed per input character
+
+    pub async fn speech(
+        &self,
+        context: &CallContext,
+        request: &SpeechRequest,
+    ) -> Result<AudioStream, CustomError> {
+        request.validate()?;
+        self.usage.check_budget(context).await?;
+        let audio = self.open_ai_api.speech(request).await?;
+        let cost_usd = request.input.chars().count() as f64 * OpenAIApi::TTS_USD_PER_MILLION_CHARS
+            / 1_000_000.0;
+        self.usage
+            .record(context, OpenAIApi::NAME, OpenAiModel::Tts.name(), None, Some(cost_usd))
+            .await;
+        Ok(audio)
+    }
+
+    // Image generation, results are kept so they can be served from `PUBLIC_BASE_URL`.
+    // Images are billed per image, SDXL on Workers AI is free
+
+    pub async fn image_generation(
+        &self,
+        context: &CallContext,
+        provider: &str,
+        request: &ImageGenerationRequest,
+    ) -> Result<ImageGenerationResponse, CustomError> {
+        if provider != OpenAIApi::NAME && provider != CloudflareApi::NAME {
+            return Err(CustomError::UnknownProvider(provider.to_string()));
+        }
+        self.usage.check_budget(context).await?;
+        let (model, images) = if provider == OpenAIApi::NAME {
+            self.open_ai_api.image_generation(request).await?
+        } else {
+            self.cloudflare_ai.image_generation(request).await?
+        };
+        let image_price = if provider == OpenAIApi::NAME {
+            OpenAIApi::image_price(&model, request.size())
+        } else {
+            0.0
+        };
+        self.usage
+            .record(context, provider, &model, None, Some(image_price * images.len() as f64))
+            .await;
+        let data = images
+            .into_iter()
+            .map(|image| {
+                let file_name = self.local_storage.save_generated(&image.bytes, "png")?;
+                Ok(match request.response_format {
+                    ImageResponseFormat::Url => GeneratedImage {
+                        url: Some(format!("{}/{}", self.images_url, file_name)),
+                        b64_json: None,
+                        revised_prompt: image.revised_prompt,
+                    },
+                    ImageResponseFormat::B64Json => GeneratedImage {
+                        url: None,
+                        b64_json: Some(general_purpose::STANDARD.encode(&image.bytes)),
+                        revised_prompt: image.revised_prompt,
+                    },
+                })
+            })
+            .collect::<Result<Vec<GeneratedImage>, CustomError>>()?;
+        Ok(ImageGenerationResponse {
+            provider: provider.to_string(),
+            model,
+            created: chrono::Utc::now().timestamp(),
+            data,
+        })
+    }
+
+    pub fn generated_image(&self, file_name: &str) -> Result<Vec<u8>, CustomError> {
+        self.local_storage.generated(file_name)
+    }
+
+    fn read(&self, f: TempFile) -> Result<Vec<u8>, CustomError> {
+        let path = self.local_storage.persist(f)?;
+        std::fs::read(path).map_err(CustomError::IoError)
+    }
+}
@@ -1,2 +1,10 @@
 pub mod api_tester_usecase;
-pub mod poi_usecase;
\ No newline at end of file
+pub mod poi_usecase;
+pub mod chat_usecase;
+pub mod admin_usecase;
+pub mod usage_usecase;
+pub mod cache_usecase;
+pub mod api_key_usecase;
+pub mod rate_limit_usecase;
+pub mod structured_output_usecase;
+pub mod media_usecase;
\ No newline at end of file
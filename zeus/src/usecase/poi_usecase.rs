@@ -7,10 +7,12 @@ use crate::{
         google_gemini::GeminiApi,
         google_places::{GoogleGeocodeApiRequest, GooglePlacesApi},
         google_vision::{GoogleVisionApi, VisionFeatures},
+        llm_provider::{ChatResponse, LlmProvider},
         open_ai::{OpenAIApi, OpenAiModel},
     },
-    models::custom_error::CustomError,
+    models::{call_context::CallContext, custom_error::CustomError},
     repository::prompt_provider,
+    usecase::usage_usecase::UsageUsecase,
     utils::image_utils::{self, ImageUtils},
 };
 
@@ -19,6 +21,7 @@ pub struct PoiUsecase {
     gemini_api: Arc<GeminiApi>,
     open_ai: Arc<OpenAIApi>,
     google_places: Arc<GooglePlacesApi>,
+    usage: Arc<UsageUsecase>,
 }
 
 impl PoiUsecase {
@@ -27,23 +30,28 @@ impl PoiUsecase {
         gemini_api: Arc<GeminiApi>,
         open_ai: Arc<OpenAIApi>,
         google_places: Arc<GooglePlacesApi>,
+        usage: Arc<UsageUsecase>,
     ) -> Self {
         Self {
             google_vision_api,
             gemini_api,
             open_ai,
             google_places,
+            usage,
         }
     }
 
     pub async fn from_image(
         &self,
+        context: &CallContext,
         location: GoogleGeocodeApiRequest,
         f: TempFile,
     ) -> Result<String, CustomError> {
         let path = f.file.path().to_str().unwrap();
         match ImageUtils::to_base64(&path) {
             Ok(base64_image) => {
+                self.usage.check_budget(context).await?;
+
                 //Vision
                 let vision = self
                     .google_vision_api
@@ -66,6 +74,10 @@ impl PoiUsecase {
                     .gemini_api
                     .visual(prompt_provider::Prompt::PoiVisual, &base64_image)
                     .await?;
+                let vision_model = self.gemini_api.vision_model().unwrap_or_default();
+                let gemini_visual = self
+                    .record(context, self.gemini_api.as_ref(), vision_model, gemini_visual)
+                    .await;
 
                 log::debug!("\n\tPOI visual Gemini: {}", gemini_visual);
 
@@ -78,6 +90,9 @@ impl PoiUsecase {
                         &base64_image,
                     )
                     .await?;
+                let gpt_visual = self
+                    .record(context, self.open_ai.as_ref(), OpenAiModel::Gpt4Visual.name(), gpt_visual)
+                    .await;
 
                 log::debug!("\n\tPOI visual GPT: {}", gpt_visual);
 
@@ -95,6 +110,9 @@ impl PoiUsecase {
                         request.as_str(),
                     )
                     .await?;
+                let poi_gpt = self
+                    .record(context, self.open_ai.as_ref(), OpenAiModel::Gpt4Turbo.name(), poi_gpt)
+                    .await;
 
                 log::debug!("\n\tPOI GPT summary: {}", poi_gpt);
 
@@ -102,10 +120,34 @@ impl PoiUsecase {
                     .gemini_api
                     .completion(prompt_provider::Prompt::Poi, request.as_str())
                     .await?;
+                let text_model = self.gemini_api.default_model();
+                let poi_gemini = self
+                    .record(context, self.gemini_api.as_ref(), text_model, poi_gemini)
+                    .await;
                 log::debug!("\n\tPOI Gemini summary: {}", poi_gemini);
                 Ok(format!("GPT: {}\nGemini: {}", poi_gpt, poi_gemini,))
             }
             Err(e) => Err(e),
         }
     }
+
+    // Priced as the requested model, OpenAI answers with the name of a snapshot
+    async fn record(
+        &self,
+        context: &CallContext,
+        provider: &dyn LlmProvider,
+        model: &str,
+        response: ChatResponse,
+    ) -> String {
+        self.usage
+            .record(
+                context,
+                provider.name(),
+                model,
+                response.usage,
+                provider.cost(model, response.usage),
+            )
+            .await;
+        response.content
+    }
 }
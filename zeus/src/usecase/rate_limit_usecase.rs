@@ -0,0 +1,176 @@
+use std::{collections::HashMap, sync::Mutex, time::Instant};
+
+use crate::{
+    models::{
+        api_key::ApiKeyIdentity,
+        rate_limit::{RateLimit, RateLimitStatus},
+    },
+    repository::secrets::{RateLimitSecrets, Secrets},
+};
+
+// In memory token buckets, limits are per gateway instance
+pub struct RateLimitUsecase {
+    limits: RateLimitSecrets,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl RateLimitUsecase {
+    // Past this size the least recently seen half of the buckets is dropped
+    const MAX_BUCKETS: usize = 10_000;
+
+    pub fn new(secrets: &Secrets) -> Self {
+        Self {
+            limits: secrets.rate_limits.clone(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn check_ip(&self, ip: &str) -> Option<RateLimitStatus> {
+        let limit = self.limits.ip?;
+        self.take(vec![(format!("ip:{}", ip), limit)])
+    }
+
+    // The key's own limit replaces the default key limit, the route limit is counted per key
+    pub fn check_key(&self, key: &ApiKeyIdentity, route: &str) -> Option<RateLimitStatus> {
+        let checks = [
+            key.rate_limit
+                .or(self.limits.key)
+                .map(|limit| (format!("key:{}", key.id), limit)),
+            self.limits
+                .routes
+                .get(route)
+                .map(|limit| (format!("route:{}:{}", route, key.id), *limit)),
+        ];
+        self.take(checks.into_iter().flatten().collect())
+    }
+
+    // Takes a token from every bucket only when all of them have one, reports the tightest
+    fn take(&self, checks: Vec<(String, RateLimit)>) -> Option<RateLimitStatus> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let now = Instant::now();
+        if buckets.len() > Self::MAX_BUCKETS {
+            Self::evict(&mut buckets);
+        }
+
+        for (id, limit) in &checks {
+            let bucket = buckets.entry(id.clone()).or_insert_with(|| Bucket::new(*limit, now));
+            bucket.limit = *limit;
+            bucket.refill(now);
+        }
+        let allowed = checks.iter().all(|(id, _)| buckets[id].tokens >= 1.0);
+
+        checks
+            .iter()
+            .map(|(id, _)| {
+                let bucket = buckets.get_mut(id).unwrap();
+                if allowed {
+                    bucket.tokens -= 1.0;
+                }
+                bucket.status(allowed)
+            })
+            .min_by_key(|status| (status.allowed, status.remaining))
+    }
+
+    // Callers still sending requests keep their buckets however many distinct clients show up
+    fn evict(buckets: &mut HashMap<String, Bucket>) {
+        let mut last_seen: Vec<Instant> = buckets.values().map(|bucket| bucket.updated_at).collect();
+        let keep = Self::MAX_BUCKETS / 2;
+        let index = last_seen.len().saturating_sub(keep);
+        let (_, cutoff, _) = last_seen.select_nth_unstable(index);
+        let cutoff = *cutoff;
+        buckets.retain(|_, bucket| bucket.updated_at >= cutoff);
+    }
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst.max(1) as f64,
+            updated_at: now,
+        }
+    }
+
+    fn capacity(&self) -> f64 {
+        self.limit.burst.max(1) as f64
+    }
+
+    fn per_second(&self) -> f64 {
+        self.limit.requests_per_minute.max(1) as f64 / 60.0
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second()).min(self.capacity());
+        self.updated_at = now;
+    }
+
+    // Reset is the wait for the next token when denied, and for a full bucket otherwise
+    fn status(&self, allowed: bool) -> RateLimitStatus {
+        let missing = if allowed {
+            self.capacity() - self.tokens
+        } else {
+            1.0 - self.tokens
+        };
+        RateLimitStatus {
+            allowed,
+            limit: self.limit.burst.max(1),
+            remaining: self.tokens.max(0.0) as u32,
+            reset_secs: (missing.max(0.0) / self.per_second()).ceil() as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usecase() -> RateLimitUsecase {
+        let secrets = Secrets {
+            rate_limits: RateLimitSecrets {
+                ip: Some(RateLimit {
+                    requests_per_minute: 1,
+                    burst: 1,
+                }),
+                key: None,
+                routes: HashMap::new(),
+            },
+            ..Secrets::default()
+        };
+        RateLimitUsecase::new(&secrets)
+    }
+
+    #[test]
+    fn denies_once_the_bucket_is_empty() {
+        let usecase = usecase();
+        assert!(usecase.check_ip("10.0.0.1").unwrap().allowed);
+        let status = usecase.check_ip("10.0.0.1").unwrap();
+        assert!(!status.allowed);
+        assert_eq!(status.remaining, 0);
+        assert!(usecase.check_ip("10.0.0.2").unwrap().allowed);
+    }
+
+    #[test]
+    fn evicts_the_least_recently_seen_buckets() {
+        let usecase = usecase();
+        usecase.check_ip("old").unwrap();
+        for i in 0..RateLimitUsecase::MAX_BUCKETS - 2 {
+            usecase.check_ip(&format!("10.0.{}.{}", i / 256, i % 256)).unwrap();
+        }
+        usecase.check_ip("hot").unwrap();
+        usecase.check_ip("new").unwrap();
+        // Past the limit drained buckets are evicted too, the recently seen one keeps its state
+        assert!(!usecase.check_ip("hot").unwrap().allowed);
+
+        let buckets = usecase.buckets.lock().unwrap();
+        assert!(buckets.len() <= RateLimitUsecase::MAX_BUCKETS / 2 + 1);
+        assert!(!buckets.contains_key("ip:old"));
+        assert!(buckets.contains_key("ip:hot"));
+    }
+}
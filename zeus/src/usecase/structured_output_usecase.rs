@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use jsonschema::JSONSchema;
+use serde_json::Value as JsonValue;
+
+use crate::{
+    api::{
+        llm_provider::{ChatRequest, ChatResponse, LlmProvider, TokenUsage},
+        provider_registry::ProviderRegistry,
+    },
+    models::{
+        call_context::CallContext,
+        chat_message::{ChatMessage, ChatRole},
+        custom_error::CustomError,
+        response_format::ResponseFormat,
+    },
+    repository::secrets::Secrets,
+    usecase::usage_usecase::UsageUsecase,
+};
+
+// Completions asked for JSON are parsed and validated, invalid answers are sent back
+// to the model with the validation errors
+pub struct StructuredOutputUsecase {
+    providers: Arc<ProviderRegistry>,
+    usage: Arc<UsageUsecase>,
+    max_retries: u32,
+}
+
+impl StructuredOutputUsecase {
+    pub fn new(
+        providers: Arc<ProviderRegistry>,
+        usage: Arc<UsageUsecase>,
+        secrets: &Secrets,
+    ) -> Self {
+        Self {
+            providers,
+            usage,
+            max_retries: secrets.structured_output_max_retries,
+        }
+    }
+
+    // Usage and cost of the returned response add up every attempt. Callers record
+    // successful responses, attempts that end in an error are recorded here
+    pub async fn chat(
+        &self,
+        context: &CallContext,
+        provider: Arc<dyn LlmProvider>,
+        request: &ChatRequest,
+    ) -> Result<ChatResponse, CustomError> {
+        let Some(format) = request
+            .response_format
+            .as_ref()
+            .filter(|format| format.is_json())
+        else {
+            return self.providers.chat(provider, request).await;
+        };
+        let schema = format
+            .schema()
+            .map(JSONSchema::compile)
+            .transpose()
+            .map_err(|e| CustomError::InvalidRequest(format!("invalid JSON schema: {}", e)))?;
+
+        let mut request = request.clone();
+        request.messages.insert(
+            0,
+            ChatMessage::new(ChatRole::System, Self::instruction(format)),
+        );
+        let mut usage: Option<TokenUsage> = None;
+        let mut cost_usd: Option<f64> = None;
+        let mut errors = String::new();
+        let mut served: Option<(String, String)> = None;
+        for attempt in 0..=self.max_retries {
+            let response = match self.providers.chat(Arc::clone(&provider), &request).await {
+                Ok(response) => response,
+                Err(e) => {
+                    self.record_failed(context, served, usage, cost_usd).await;
+                    return Err(e);
+                }
+            };
+            served = Some((response.provider.clone(), response.model.clone()));
+            usage = Self::add_usage(usage, response.usage);
+            cost_usd = Self::add_cost(cost_usd, response.cost_usd);
+            if !response.tool_calls.is_empty() {
+                return Ok(ChatResponse {
+                    usage,
+                    cost_usd,
+                    ..response
+                });
+            }
+            match Self::parse(&response.content, schema.as_ref()) {
+                Ok(value) => {
+                    return Ok(ChatResponse {
+                        content: value.to_string(),
+                        usage,
+                        cost_usd,
+                        ..response
+                    })
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Invalid JSON from {} on attempt {}: {}",
+                        response.model,
+                        attempt + 1,
+                        e
+                    );
+                    request
+                        .messages
+                        .push(ChatMessage::new(ChatRole::Assistant, response.content));
+                    request.messages.push(ChatMessage::new(
+                        ChatRole::User,
+                        format!(
+                            "Your answer is not valid: {}. Reply again with only the corrected JSON.",
+                            e
+                        ),
+                    ));
+                    errors = e;
+                }
+            }
+        }
+        self.record_failed(context, served, usage, cost_usd).await;
+        Err(CustomError::SchemaViolation(format!(
+            "no valid JSON after {} attempts: {}",
+            self.max_retries + 1,
+            errors
+        )))
+    }
+
+    // Paid attempts are charged even when no valid answer came out of them
+    async fn record_failed(
+        &self,
+        context: &CallContext,
+        served: Option<(String, String)>,
+        usage: Option<TokenUsage>,
+        cost_usd: Option<f64>,
+    ) {
+        if let Some((provider, model)) = served {
+            self.usage
+                .record(context, &provider, &model, usage, cost_usd)
+                .await;
+        }
+    }
+
+    fn instruction(format: &ResponseFormat) -> String {
+        match format.schema() {
+            Some(schema) => format!(
+                "Answer only with JSON matching this JSON schema, without any other text: {}",
+                schema
+            ),
+            None => "Answer only with a JSON object, without any other text.".to_string(),
+        }
+    }
+
+    fn parse(content: &str, schema: Option<&JSONSchema>) -> Result<JsonValue, String> {
+        let value = Self::extract(content).ok_or("the answer does not contain JSON")?;
+        let Some(schema) = schema else {
+            return Ok(value);
+        };
+        if let Err(errors) = schema.validate(&value) {
+            return Err(errors
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    format!("{}: {}", if path.is_empty() { "/" } else { &path }, e)
+                })
+                .collect::<Vec<String>>()
+                .join("; "));
+        }
+        Ok(value)
+    }
+
+    // The whole answer, a fenced ```json block, or the outermost object or array
+    fn extract(content: &str) -> Option<JsonValue> {
+        let content = content.trim();
+        if let Ok(value) = serde_json::from_str(content) {
+            return Some(value);
+        }
+        let fenced = content.split("```").nth(1).map(|block| {
+            block
+                .strip_prefix("json")
+                .or_else(|| block.strip_prefix("JSON"))
+                .unwrap_or(block)
+        });
+        if let Some(Ok(value)) = fenced.map(|block| serde_json::from_str(block.trim())) {
+            return Some(value);
+        }
+        let start = content.find(['{', '['])?;
+        let end = content.rfind(['}', ']'])?;
+        serde_json::from_str(content.get(start..=end)?).ok()
+    }
+
+    fn add_usage(total: Option<TokenUsage>, usage: Option<TokenUsage>) -> Option<TokenUsage> {
+        match (total, usage) {
+            (Some(total), Some(usage)) => Some(TokenUsage {
+                prompt_tokens: total.prompt_tokens + usage.prompt_tokens,
+                completion_tokens: total.completion_tokens + usage.completion_tokens,
+                total_tokens: total.total_tokens + usage.total_tokens,
+            }),
+            (total, usage) => total.or(usage),
+        }
+    }
+
+    fn add_cost(total: Option<f64>, cost: Option<f64>) -> Option<f64> {
+        match (total, cost) {
+            (Some(total), Some(cost)) => Some(total + cost),
+            (total, cost) => total.or(cost),
+        }
+    }
+}
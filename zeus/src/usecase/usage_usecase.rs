@@ -0,0 +1,273 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{Datelike, NaiveDate, Utc};
+use futures_util::{future, StreamExt};
+
+use crate::{
+    api::{
+        llm_provider::{ChatDelta, ChatRequest, MeteredStream, RoutedStream, TokenUsage},
+        model_catalog::ModelPrice,
+    },
+    models::{call_context::CallContext, custom_error::CustomError, usage_query::UsageQuery},
+    repository::{
+        secrets::{BudgetSecrets, Secrets},
+        usage_repository::{UsageAggregateEntity, UsageRecord, UsageRepository},
+    },
+};
+
+// Token and spend accounting shared by the completion usecases
+pub struct UsageUsecase {
+    usage: Arc<UsageRepository>,
+    budgets: HashMap<String, BudgetSecrets>,
+}
+
+impl UsageUsecase {
+    const KEY_BUDGET_PREFIX: &'static str = "key:";
+
+    pub fn new(usage: Arc<UsageRepository>, secrets: &Secrets) -> Self {
+        Self {
+            usage,
+            budgets: secrets.spend_budgets.clone(),
+        }
+    }
+
+    // Projects without a budget of their own fall under the "default" one, if any.
+    // API keys are also held to their own `key:{id}` budget
+    pub async fn check_budget(&self, context: &CallContext) -> Result<(), CustomError> {
+        let today = Utc::now().date_naive();
+        let month = NaiveDate::from_ymd_opt(today.year(), today.month(), 1).unwrap_or(today);
+        let day_start = today.and_time(Default::default()).and_utc();
+        let month_start = month.and_time(Default::default()).and_utc();
+
+        if let Some(budget) = self
+            .budgets
+            .get(&context.project)
+            .or_else(|| self.budgets.get(CallContext::DEFAULT_PROJECT))
+        {
+            let spent = self
+                .usage
+                .spend(&context.project, day_start, month_start)
+                .await?;
+            Self::within(budget, spent, &format!("project {}", context.project))?;
+        }
+        if let Some(key) = &context.api_key {
+            let key_budget = format!("{}{}", Self::KEY_BUDGET_PREFIX, key.id);
+            if let Some(budget) = self.budgets.get(&key_budget) {
+                let spent = self.usage.key_spend(key.id, day_start, month_start).await?;
+                Self::within(budget, spent, &format!("API key {}", key.name))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn within(
+        budget: &BudgetSecrets,
+        (daily, monthly): (f64, f64),
+        owner: &str,
+    ) -> Result<(), CustomError> {
+        for (period, limit, spent) in [
+            ("daily", budget.daily_usd, daily),
+            ("monthly", budget.monthly_usd, monthly),
+        ] {
+            if let Some(limit) = limit {
+                if spent >= limit {
+                    return Err(CustomError::BudgetExceeded(format!(
+                        "{} spent ${:.2} of its {} budget of ${:.2}",
+                        owner, spent, period, limit
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Accounting must not fail the completion itself
+    pub async fn record(
+        &self,
+        context: &CallContext,
+        provider: &str,
+        model: &str,
+        usage: Option<TokenUsage>,
+        cost_usd: Option<f64>,
+    ) {
+        let record = UsageRecord {
+            endpoint: context.endpoint.clone(),
+            project: context.project.clone(),
+            api_key_id: context.api_key.as_ref().map(|key| key.id),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            usage,
+            cost_usd,
+        };
+        if let Err(e) = self.usage.insert_one(&record).await {
+            log::error!("Failed to record usage {:?}: {}", record, e);
+        }
+    }
+
+    // Streamed calls are recorded when their stream is dropped, finished or not
+    pub fn meter(
+        self: Arc<Self>,
+        context: &CallContext,
+        request: &ChatRequest,
+        routed: RoutedStream,
+    ) -> MeteredStream {
+        let mut meter = StreamMeter {
+            usage: self,
+            context: context.clone(),
+            provider: routed.provider.clone(),
+            model: routed.model.clone(),
+            tally: StreamTally::new(request, routed.price),
+        };
+        let stream = routed
+            .stream
+            .filter_map(move |delta| future::ready(meter.tally.observe(delta)))
+            .boxed();
+        MeteredStream {
+            provider: routed.provider,
+            model: routed.model,
+            stream,
+        }
+    }
+
+    pub async fn daily(&self, query: &UsageQuery) -> Result<Vec<UsageAggregateEntity>, CustomError> {
+        self.usage.aggregate_daily(query.from, query.to).await
+    }
+}
+
+struct StreamMeter {
+    usage: Arc<UsageUsecase>,
+    context: CallContext,
+    provider: String,
+    model: String,
+    tally: StreamTally,
+}
+
+impl Drop for StreamMeter {
+    fn drop(&mut self) {
+        if self.tally.reported.is_none() {
+            log::info!("{} did not report stream usage, estimating it", self.provider);
+        }
+        let token_usage = self.tally.token_usage();
+        let cost_usd = self.tally.cost_usd();
+        let usage = Arc::clone(&self.usage);
+        let context = self.context.clone();
+        let provider = std::mem::take(&mut self.provider);
+        let model = std::mem::take(&mut self.model);
+        tokio::spawn(async move {
+            usage
+                .record(&context, &provider, &model, Some(token_usage), cost_usd)
+                .await;
+        });
+    }
+}
+
+// What a stream used so far, priced like a whole completion so streams count against budgets
+struct StreamTally {
+    price: Option<ModelPrice>,
+    prompt_chars: usize,
+    completion_chars: usize,
+    reported: Option<TokenUsage>,
+}
+
+impl StreamTally {
+    // Rough count for providers that do not report the usage of a stream
+    const CHARS_PER_TOKEN: usize = 4;
+
+    fn new(request: &ChatRequest, price: Option<ModelPrice>) -> Self {
+        Self {
+            price,
+            prompt_chars: request
+                .messages
+                .iter()
+                .map(|message| message.content.chars().count())
+                .sum(),
+            completion_chars: 0,
+            reported: None,
+        }
+    }
+
+    // Passes the text on and keeps the usage for the record
+    fn observe(
+        &mut self,
+        delta: Result<ChatDelta, CustomError>,
+    ) -> Option<Result<String, CustomError>> {
+        match delta {
+            Ok(ChatDelta::Text(text)) => {
+                self.completion_chars += text.chars().count();
+                Some(Ok(text))
+            }
+            Ok(ChatDelta::Usage(usage)) => {
+                self.reported = Some(usage);
+                None
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    fn token_usage(&self) -> TokenUsage {
+        self.reported.unwrap_or_else(|| {
+            let prompt_tokens = self.prompt_chars.div_ceil(Self::CHARS_PER_TOKEN) as u32;
+            let completion_tokens = self.completion_chars.div_ceil(Self::CHARS_PER_TOKEN) as u32;
+            TokenUsage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            }
+        })
+    }
+
+    fn cost_usd(&self) -> Option<f64> {
+        self.price.map(|price| price.cost(&self.token_usage()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        chat_message::{ChatMessage, ChatRole},
+        sampling_params::SamplingParams,
+    };
+
+    fn tally(price: Option<ModelPrice>) -> StreamTally {
+        let request = ChatRequest {
+            model: "model".to_string(),
+            messages: vec![ChatMessage::new(ChatRole::User, "a".repeat(400))],
+            params: SamplingParams::default(),
+            tools: Vec::new(),
+            tool_choice: None,
+            response_format: None,
+            safety_settings: Vec::new(),
+        };
+        StreamTally::new(&request, price)
+    }
+
+    #[test]
+    fn streamed_cost_uses_reported_usage() {
+        let mut tally = tally(Some(ModelPrice::new(1.0, 2.0)));
+        assert!(tally.observe(Ok(ChatDelta::Text("hello".to_string()))).is_some());
+        let usage = TokenUsage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 500_000,
+            total_tokens: 1_500_000,
+        };
+        assert!(tally.observe(Ok(ChatDelta::Usage(usage))).is_none());
+        assert_eq!(tally.cost_usd(), Some(2.0));
+    }
+
+    #[test]
+    fn streamed_cost_is_estimated_without_reported_usage() {
+        let mut tally = tally(Some(ModelPrice::new(1_000_000.0, 1_000_000.0)));
+        tally.observe(Ok(ChatDelta::Text("a".repeat(40))));
+        let usage = tally.token_usage();
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens), (100, 10));
+        assert_eq!(tally.cost_usd(), Some(110.0));
+    }
+
+    #[test]
+    fn streamed_cost_is_unknown_without_price() {
+        let mut tally = tally(None);
+        tally.observe(Ok(ChatDelta::Text("hello".to_string())));
+        assert_eq!(tally.cost_usd(), None);
+    }
+}
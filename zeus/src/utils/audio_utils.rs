@@ -0,0 +1,79 @@
+use std::{fs::File, io::ErrorKind, path::Path};
+
+use symphonia::core::{
+    errors::Error as SymphoniaError, formats::FormatOptions, io::MediaSourceStream,
+    meta::MetadataOptions, probe::Hint, units::TimeBase,
+};
+
+use crate::models::custom_error::CustomError;
+
+pub struct AudioUtils {}
+
+impl AudioUtils {
+    // Formats accepted by both OpenAI and Cloudflare Whisper
+    pub const EXTENSIONS: [&'static str; 9] = [
+        "flac", "m4a", "mp3", "mp4", "mpeg", "mpga", "ogg", "wav", "webm",
+    ];
+
+    pub fn extension(file_name: &str) -> Option<String> {
+        Path::new(file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase())
+    }
+
+    // Read from the container, packets are only walked when it doesn't carry a frame count.
+    // Uploads are probed before they are kept, their temporary files have no extension
+    pub fn duration_secs(audio_path: &Path, extension: &str) -> Result<f64, CustomError> {
+        let file = File::open(audio_path).map_err(CustomError::IoError)?;
+        let mut hint = Hint::new();
+        hint.with_extension(extension);
+        let mut format = symphonia::default::get_probe()
+            .format(
+                &hint,
+                MediaSourceStream::new(Box::new(file), Default::default()),
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(Self::unreadable)?
+            .format;
+        let track = format
+            .default_track()
+            .ok_or_else(|| CustomError::InvalidRequest("audio has no track".to_string()))?;
+        let track_id = track.id;
+        let time_base = track
+            .codec_params
+            .time_base
+            .or_else(|| {
+                track
+                    .codec_params
+                    .sample_rate
+                    .map(|rate| TimeBase::new(1, rate))
+            })
+            .ok_or_else(|| CustomError::InvalidRequest("audio has no time base".to_string()))?;
+
+        let frames = match track.codec_params.n_frames {
+            Some(frames) => frames,
+            None => {
+                let mut frames = 0;
+                loop {
+                    match format.next_packet() {
+                        Ok(packet) if packet.track_id() == track_id => frames += packet.dur,
+                        Ok(_) => {}
+                        Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                            break
+                        }
+                        Err(e) => return Err(Self::unreadable(e)),
+                    }
+                }
+                frames
+            }
+        };
+        let time = time_base.calc_time(frames);
+        Ok(time.seconds as f64 + time.frac)
+    }
+
+    fn unreadable(error: SymphoniaError) -> CustomError {
+        CustomError::InvalidRequest(format!("unreadable audio: {}", error))
+    }
+}
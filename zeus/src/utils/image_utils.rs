@@ -12,4 +12,21 @@ impl ImageUtils {
             Err(e) => return Err(CustomError::IoError(e)),
         }
     }
+
+    // Media type from the magic bytes of a base64 image, JPEG when unknown
+    pub fn media_type(base64: &str) -> &'static str {
+        // 16 base64 characters decode to the first 12 bytes
+        let head = general_purpose::STANDARD
+            .decode(base64.get(..16).unwrap_or(base64))
+            .unwrap_or_default();
+        if head.starts_with(b"\x89PNG") {
+            "image/png"
+        } else if head.starts_with(b"GIF8") {
+            "image/gif"
+        } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP") {
+            "image/webp"
+        } else {
+            "image/jpeg"
+        }
+    }
 }
@@ -1,2 +1,4 @@
 pub mod image_utils;
-pub mod gps_utils;
\ No newline at end of file
+pub mod gps_utils;
+pub mod sse_utils;
+pub mod audio_utils;
\ No newline at end of file
@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+use futures_util::{stream, Stream, StreamExt};
+
+use crate::{api::llm_provider::TextStream, models::custom_error::CustomError};
+
+pub struct SseUtils {}
+
+impl SseUtils {
+    pub const DONE: &'static str = "[DONE]";
+
+    // Splits a server-sent events body into the payloads of its `data:` fields
+    pub fn data_events(response: reqwest::Response) -> TextStream {
+        Self::parse(response.bytes_stream())
+    }
+
+    // Lines may be split across chunks and end with LF or CRLF, the `data:` lines of an event
+    // are joined with LF
+    fn parse<S, B>(chunks: S) -> TextStream
+    where
+        S: Stream<Item = Result<B, reqwest::Error>> + Send + 'static,
+        B: AsRef<[u8]> + Send + 'static,
+    {
+        let state = (
+            chunks.fuse().boxed(),
+            Vec::<u8>::new(),
+            Vec::<String>::new(),
+            VecDeque::<String>::new(),
+        );
+        stream::unfold(
+            state,
+            |(mut bytes, mut buffer, mut data, mut events)| async move {
+                loop {
+                    if let Some(event) = events.pop_front() {
+                        return Some((Ok(event), (bytes, buffer, data, events)));
+                    }
+                    match bytes.next().await {
+                        Some(Ok(chunk)) => {
+                            buffer.extend_from_slice(chunk.as_ref());
+                            while let Some(position) = buffer.iter().position(|b| *b == b'\n') {
+                                let line: Vec<u8> = buffer.drain(..=position).collect();
+                                let line = String::from_utf8_lossy(&line);
+                                let line = line.trim_end_matches(['\r', '\n']);
+                                if line.is_empty() {
+                                    if !data.is_empty() {
+                                        events.push_back(data.join("\n"));
+                                        data.clear();
+                                    }
+                                } else if let Some(value) = line.strip_prefix("data:") {
+                                    // Only a single leading space belongs to the field syntax
+                                    data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+                                }
+                            }
+                        }
+                        Some(Err(e)) => {
+                            return Some((Err(CustomError::from(e)), (bytes, buffer, data, events)))
+                        }
+                        None if !data.is_empty() => events.push_back(data.split_off(0).join("\n")),
+                        None => return None,
+                    }
+                }
+            },
+        )
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt;
+
+    use super::*;
+
+    async fn events(chunks: &[&'static str]) -> Vec<String> {
+        let chunks: Vec<Result<&'static [u8], reqwest::Error>> =
+            chunks.iter().map(|chunk| Ok(chunk.as_bytes())).collect();
+        SseUtils::parse(stream::iter(chunks))
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn yields_the_data_of_each_event() {
+        let body = "event: message\ndata: {\"a\":1}\n\n: comment\ndata: [DONE]\n\n";
+        assert_eq!(events(&[body]).await, vec!["{\"a\":1}", SseUtils::DONE]);
+    }
+
+    #[tokio::test]
+    async fn joins_multi_line_data() {
+        let body = "data: first\ndata: second\ndata:third\n\n";
+        assert_eq!(events(&[body]).await, vec!["first\nsecond\nthird"]);
+    }
+
+    #[tokio::test]
+    async fn accepts_crlf_line_endings() {
+        let body = "data: one\r\n\r\ndata: two\r\ndata: three\r\n\r\n";
+        assert_eq!(events(&[body]).await, vec!["one", "two\nthree"]);
+    }
+
+    #[tokio::test]
+    async fn reassembles_events_split_across_chunks() {
+        let chunks = ["da", "ta: {\"text\":", "\"hi\"}\r", "\n", "\r\nda", "ta: bye\n", "\n"];
+        assert_eq!(events(&chunks).await, vec!["{\"text\":\"hi\"}", "bye"]);
+    }
+
+    #[tokio::test]
+    async fn flushes_an_unterminated_last_event() {
+        assert_eq!(events(&["data: a\n\ndata: b"]).await, vec!["a"]);
+        assert_eq!(events(&["data: a\n\ndata: b\n"]).await, vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn keeps_spaces_beyond_the_first() {
+        assert_eq!(events(&["data:   indented\n\n"]).await, vec!["  indented"]);
+    }
+}